@@ -1,33 +1,152 @@
 use eframe::egui;
 use egui_extras::{Column, TableBuilder};
-use ssh2::Session;
+use ssh2::{FileStat, Session};
 use std::path::PathBuf;
+use std::sync::atomic::{AtomicBool, Ordering};
 use std::sync::{Arc, Mutex, mpsc};
 use std::thread;
+use std::time::Duration;
 
-use crate::model::{FileEncoding, FileEntry, SortColumn, SortDirection};
-use crate::ssh::{connect_session, download_worker, list_files_streaming, search_files_streaming};
+use crate::data_view::{DataViewState, JsonDocument, MAX_TREE_CHILDREN, delimiter_name};
+use crate::decompress::{
+    Compression, Decompressed, MAX_SPOOL_BYTES, SpoolFile, Spooling, decompress_remote,
+    read_spool_range,
+};
+use crate::dir_tree::{DirTree, TreeRow};
+use crate::external_edit::ExternalEdit;
+use crate::find::{
+    FindMode, FindState, find_bytes_regex, find_regex, highlight_matches, line_start,
+};
+use crate::follow::FileFollow;
+use crate::grep::{ContentSearch, GrepRequest};
+use crate::hex_view::{
+    BYTES_PER_ROW, HexViewState, format_hex_bytes, is_binary, parse_hex_pattern, printable_char,
+};
+use crate::highlight::{HighlightCache, MAX_HIGHLIGHT_BYTES, detect_syntax, syntax_names};
+use crate::image_view::{ImageViewState, MAX_IMAGE_BYTES, image_extension, is_image_path};
+use crate::model::{
+    AppSettings, ContentHit, EncodingRule, FileEncoding, FileEntry, FollowUpdate, IdNames,
+    LineEnding, LineIndexUpdate, OwnerField, PermissionChange, SavedSearch, SearchQuery,
+    SortColumn, SortDirection, ViewMode, push_search_history,
+};
+use crate::nav_history::NavHistory;
+use crate::paged_view::{PAGE_SIZE, PagedFile, parse_offset};
+use crate::path_bar::{MAX_SUGGESTIONS, PathCompletion, breadcrumbs, split_input};
+use crate::remote_find::find_files_streaming;
+use crate::search_filter::{EntryType, SearchFilter};
+use crate::ssh::{
+    RemoteModifiedError, apply_permissions, build_line_index, connect_session, create_symlink,
+    download_worker, format_mode_octal, format_timestamp_full, list_files_streaming,
+    list_subdirectories, load_id_names, matches_pattern, open_locked, parse_mode_octal,
+    read_file_range, search_files_streaming, stat_path, write_file_content,
+};
+use crate::walk::WalkLimit;
+
+/// Files up to this size are loaded whole into the editable viewer;
+/// larger files open in the paged, read-only viewer.
+const FULL_LOAD_LIMIT: usize = 1024 * 1024;
 use ssh2::Sftp;
 
 struct FileViewerState {
     filename: String,
     raw_content: Vec<u8>,
-    decoded_content: String,
+    decoded_content: String, // "\n" line breaks unless line_ending is Mixed
+    has_text: bool,          // false for images loaded past FULL_LOAD_LIMIT; no Text tab
+    revision: u64,           // bumped on every change to decoded_content; keys the caches
     encoding: FileEncoding,
+    line_ending: LineEnding, // style written on save
+    bom: bool,               // write a byte order mark on save
+    mtime: Option<u64>,      // remote mtime when loaded, for conflict detection
+    truncated: bool,         // only part of the file was loaded; saving would cut it off
+    modified: bool,
+    is_saving: bool,
+    conflict: bool,
+    backed_up: bool, // <path>.bak holds the pre-edit file; only the first save writes it
+    paged: Option<PagedFile>, // set for files larger than FULL_LOAD_LIMIT
+    goto_input: String,
+    follow: Option<FileFollow>, // live tail mode
+    view_mode: ViewMode,
+    hex: HexViewState,
+    remember_pattern: Option<String>, // shown while editing an encoding rule
+    syntax: Option<String>,           // None = plain text
+    highlight_cache: HighlightCache,
+    find: FindState,
+    line_input: String,
+    image: Option<ImageViewState>, // set when the content decodes as an image
+    compression: Option<Compression>, // decompressed on load; read-only
+    spool: Option<Arc<SpoolFile>>, // full decompressed output when paged
+    spooling: Option<u64>,         // id of the decompression still filling the spool
+    spool_capped: bool,            // output stopped at MAX_SPOOL_BYTES
+    data: DataViewState,           // JSON tree and CSV/TSV table
+    pending_line: Option<u64>,     // line to bring into view (content search hit)
+}
+
+struct PropertiesDialogState {
+    path: String,
+    is_dir: bool,
+    dir_mode: u32,
+    dir_mode_input: String,
+    file_mode: u32,
+    file_mode_input: String,
+    uid: OwnerField,
+    gid: OwnerField,
+    recursive: bool,
+}
+
+/// File name search in progress; setting `cancel` stops the worker.
+/// Messages from a search that was replaced carry a different `id`.
+struct NameSearch {
+    id: u64,
+    query: String,
+    base: String, // directory searched; not necessarily the one shown
+    cancel: Arc<AtomicBool>,
+}
+
+struct SymlinkDialogState {
+    link_name: String,
+    target: String,
 }
 
 // Removed duplicate FileViewerState enum
 
+/// (session, sftp, initial path)
+type ConnectionHandles = (Arc<Mutex<Session>>, Arc<Mutex<Sftp>>, String);
+
 pub enum AppMessage {
-    ConnectionResult(Result<(Arc<Mutex<Session>>, Arc<Mutex<Sftp>>, String), String>), // (session, sftp, path)
+    ConnectionResult(Result<ConnectionHandles, String>),
     // ListResult removed
     ListStarted(String),
     ListBatch(Vec<FileEntry>),
     ListFinished,
     ListError(String),
-    SearchStarted(String),
+    SearchStarted(u64),                                  // (search id)
+    SearchBatch(u64, Vec<FileEntry>),                    // (search id, matches)
+    SearchProgress(u64, usize, usize), // (search id, directories scanned, matches found)
+    SearchFinished(u64, usize, bool, Option<WalkLimit>), // (search id, directories scanned, stopped by the user, cap hit)
+    SearchError(u64, String),                            // (search id, error)
     DownloadResult(Result<String, String>),
-    FileContentResult(Result<(String, Vec<u8>), String>), // (filename, raw_content)
+    FileContentResult(Result<(String, Vec<u8>, FileStat), String>), // (filename, raw_content, stat)
+    SaveResult(String, Result<(Vec<u8>, Option<u64>), String>), // (filename, (saved_content, new mtime))
+    SaveConflict(String),
+    FileRangeResult(String, u64, Result<Vec<u8>, String>), // (filename, page, bytes)
+    LineIndexProgress(String, LineIndexUpdate),
+    DecompressProgress(u64, String, u64), // (spooling id, filename, bytes flushed so far)
+    DecompressResult(String, Result<Decompressed, String>),
+    DecompressFinished(u64, String, Result<(u64, bool), String>), // (spooling id, filename, (size, capped))
+    FollowResult(u64, String, FollowUpdate),                      // (follow id, filename, update)
+    ContentSearchBatch(u64, Vec<ContentHit>, usize), // (search id, hits, files scanned so far)
+    ContentSearchFinished(u64, Result<Option<String>, String>), // (search id, why it ended early)
+    ExternalEditStarted(String),                     // remote path
+    ExternalEditUploaded(String),
+    ExternalEditConflict(String),
+    ExternalEditError(String, String), // (remote path, error)
+    ExternalEditStopped(PathBuf),      // local copy, unique per edit
+    PropertiesResult(Result<(String, FileStat), String>), // (path, stat)
+    PermissionsResult(Result<String, String>),
+    SymlinkResult(Result<String, String>),
+    IdNamesLoaded(IdNames),
+    PathCompletions(String, Vec<String>), // (directory, subdirectory names)
+    TreeChildren(String, Result<Vec<String>, String>), // (directory, subdirectory names)
 }
 
 pub struct SshApp {
@@ -35,6 +154,7 @@ pub struct SshApp {
     session: Option<Arc<Mutex<Session>>>,
     sftp: Option<Arc<Mutex<Sftp>>>,
     is_connected: bool,
+    id_names: IdNames, // uid/gid names, cached per session
 
     // Login Data
     host: String,
@@ -45,25 +165,65 @@ pub struct SshApp {
     favorites: Vec<crate::model::FavoriteConnection>,
     favorite_name_input: String,
 
+    // Settings
+    settings: AppSettings,
+
     // Directory Bookmarks
     directory_bookmarks: Vec<crate::model::DirectoryBookmark>,
     bookmark_name_input: String,
 
+    // Saved searches and recent-search history (newest first)
+    saved_searches: Vec<SavedSearch>,
+    search_history: Vec<SearchQuery>,
+    saved_search_name_input: String,
+
     // File Browser State
     files: Vec<FileEntry>,
     selected_file: Option<FileEntry>,
     current_path: String,
-    search_query: String,
-    recursive_search: bool,
+    show_details: bool,
+    show_tree: bool,
+    dir_tree: DirTree,
+    // Scroll the tree to the current directory once it shows up
+    tree_scroll_to_current: bool,
+    search_filter: SearchFilter,
+    nav_history: NavHistory,
+    // Address bar: breadcrumb unless the path is being typed
+    path_editing: bool,
+    path_input: String,
+    focus_path_input: bool,
+    path_completion: PathCompletion,
+    name_search: Option<NameSearch>,
+    // Last id handed to a background worker; messages from replaced workers carry older ids
+    last_worker_id: u64,
+    // Directory the listed search results are relative to; None for a plain listing
+    search_base: Option<String>,
+    // Entry to select once its directory has been listed ("Reveal in folder")
+    pending_reveal: Option<String>,
+    content_query: String,
+    content_mode: FindMode,
+    content_search: Option<ContentSearch>,
+    // Content search hit to scroll to once its file has loaded
+    pending_hit: Option<ContentHit>,
 
     // File Viewer State
     viewing_file: Option<FileViewerState>,
 
+    // Files open in an external editor
+    external_edits: Vec<ExternalEdit>,
+
+    // Properties Dialog State
+    properties_dialog: Option<PropertiesDialogState>,
+
+    // Symlink Dialog State
+    symlink_dialog: Option<SymlinkDialogState>,
+
     // UI State
     status_msg: String,
     is_loading: bool,
     sort_column: SortColumn,
     sort_direction: SortDirection,
+    scroll_to_selected: bool,
 
     // Concurrency
     receiver: mpsc::Receiver<AppMessage>,
@@ -77,23 +237,51 @@ impl SshApp {
             session: None,
             sftp: None,
             is_connected: false,
+            id_names: IdNames::default(),
             host: "0.0.0.0:22".to_owned(),
             user: "".to_owned(),
             password: "".to_owned(),
             favorites: Vec::new(),
             favorite_name_input: String::new(),
+            settings: AppSettings::default(),
             directory_bookmarks: Vec::new(),
             bookmark_name_input: String::new(),
+            saved_searches: Vec::new(),
+            search_history: Vec::new(),
+            saved_search_name_input: String::new(),
             files: Vec::new(),
             selected_file: None,
             current_path: String::new(),
-            search_query: String::new(),
-            recursive_search: false,
+            show_details: false,
+            show_tree: false,
+            dir_tree: DirTree::default(),
+            tree_scroll_to_current: false,
+            search_filter: SearchFilter {
+                one_filesystem: true,
+                ..Default::default()
+            },
+            nav_history: NavHistory::default(),
+            path_editing: false,
+            path_input: String::new(),
+            focus_path_input: false,
+            path_completion: PathCompletion::default(),
+            name_search: None,
+            last_worker_id: 0,
+            search_base: None,
+            pending_reveal: None,
+            content_query: String::new(),
+            content_mode: FindMode::IgnoreCase,
+            content_search: None,
+            pending_hit: None,
             viewing_file: None,
+            external_edits: Vec::new(),
+            properties_dialog: None,
+            symlink_dialog: None,
             status_msg: "Ready to connect.".to_owned(),
             is_loading: false,
             sort_column: SortColumn::None,
             sort_direction: SortDirection::Asc,
+            scroll_to_selected: false,
             receiver,
             sender,
         };
@@ -101,10 +289,15 @@ impl SshApp {
         println!("App loading favorites...");
         app.favorites = app.load_favorites();
         app.directory_bookmarks = app.load_directory_bookmarks();
+        app.saved_searches = app.load_saved_searches();
+        app.search_history = app.load_search_history();
+        app.settings = app.load_settings();
         println!("App initialized.");
 
         // Configure fonts for Japanese support
         app.configure_fonts(&_cc.egui_ctx);
+        // PNG/JPEG/GIF/WebP/SVG decoders for the image preview
+        egui_extras::install_image_loaders(&_cc.egui_ctx);
 
         app
     }
@@ -182,7 +375,11 @@ impl SshApp {
                         path.clone(),
                     ))));
                     // Start listing immediately after connection
-                    let _ = list_files_streaming(&sftp_arc, &path, tx);
+                    let _ = list_files_streaming(&sftp_arc, &path, tx.clone());
+                    // Resolve owner/group names once per session
+                    if let Ok(names) = load_id_names(&sftp_arc) {
+                        let _ = tx.send(AppMessage::IdNamesLoaded(names));
+                    }
                 }
                 Err(e) => {
                     let _ = tx.send(AppMessage::ConnectionResult(Err(e.to_string())));
@@ -204,22 +401,243 @@ impl SshApp {
         }
     }
 
-    fn search_files(&self) {
+    /// Relists the current directory after a change to it, unless search
+    /// results are showing in its place.
+    fn refresh_listing(&mut self) {
+        if self.search_base.is_none() && self.name_search.is_none() {
+            self.is_loading = true;
+            self.list_directory(self.current_path.clone());
+        }
+    }
+
+    /// A fresh id for a worker whose messages must not be mixed up with
+    /// those of the worker it replaces.
+    fn next_worker_id(&mut self) -> u64 {
+        self.last_worker_id += 1;
+        self.last_worker_id
+    }
+
+    /// The search bar contents as a query that can be run again later.
+    fn current_search_query(&self) -> SearchQuery {
+        SearchQuery {
+            host: self.host.clone(),
+            base_path: self.current_path.clone(),
+            filter: self.search_filter.clone(),
+            content_query: self.content_query.clone(),
+            content_mode: self.content_mode,
+        }
+    }
+
+    /// Runs the search bar's search (by content if text to find is given)
+    /// under `base_path` and records it in the history. The folder shown
+    /// stays as it is.
+    fn run_search(&mut self, base_path: String) {
+        let by_content = !self.content_query.is_empty();
+        if !by_content && self.search_filter.is_unrestricted() {
+            return;
+        }
+        let valid = self.search_filter.compile().is_ok()
+            && (!by_content || find_regex(&self.content_query, self.content_mode).is_ok());
+        if valid {
+            let query = SearchQuery {
+                base_path: base_path.clone(),
+                ..self.current_search_query()
+            };
+            push_search_history(&mut self.search_history, query);
+            self.save_search_history();
+        }
+        if by_content {
+            self.search_content(base_path);
+        } else {
+            self.search_files(base_path);
+        }
+    }
+
+    /// Loads a saved or recent search into the search bar and runs it from
+    /// its base directory.
+    fn rerun_search(&mut self, query: SearchQuery) {
+        self.search_filter = query.filter;
+        self.content_query = query.content_query;
+        self.content_mode = query.content_mode;
+        self.run_search(query.base_path);
+    }
+
+    /// Searches by name and attributes under `path`, using server-side
+    /// `find` when enabled and available and SFTP otherwise.
+    fn search_files(&mut self, path: String) {
         let sftp_arc = self.sftp.clone();
+        let session_arc = self
+            .session
+            .clone()
+            .filter(|_| self.settings.server_side_find);
         let tx = self.sender.clone();
-        let path = self.current_path.clone();
-        let query = self.search_query.clone();
-        let recursive = self.recursive_search;
+        let query = match self.search_filter.pattern.trim() {
+            "" => "(filters)".to_string(),
+            pattern => pattern.to_string(),
+        };
+        let filter = match self.search_filter.compile() {
+            Ok(filter) => filter,
+            Err(e) => {
+                self.status_msg = e;
+                return;
+            }
+        };
 
         if let Some(sftp_arc) = sftp_arc {
+            self.is_loading = true;
+            let id = self.next_worker_id();
+            let cancel = Arc::new(AtomicBool::new(false));
+            let previous = self.name_search.replace(NameSearch {
+                id,
+                query,
+                base: path.clone(),
+                cancel: cancel.clone(),
+            });
+            if let Some(previous) = previous {
+                previous.cancel.store(true, Ordering::SeqCst);
+            }
             thread::spawn(move || {
-                if let Err(e) =
-                    search_files_streaming(&sftp_arc, &path, &query, recursive, tx.clone())
-                {
-                    let _ = tx.send(AppMessage::ListError(e.to_string()));
+                let result = match &session_arc {
+                    Some(session_arc) => find_files_streaming(
+                        session_arc,
+                        &sftp_arc,
+                        &path,
+                        &filter,
+                        id,
+                        &cancel,
+                        &tx,
+                    ),
+                    None => Ok(false),
                 }
+                .and_then(|done| {
+                    if done {
+                        Ok(())
+                    } else {
+                        search_files_streaming(&sftp_arc, &path, &filter, id, &cancel, tx.clone())
+                    }
+                });
+                if let Err(e) = result {
+                    let _ = tx.send(AppMessage::SearchError(id, e.to_string()));
+                }
+            });
+        }
+    }
+
+    /// Searches file contents under `base_path`; the search bar filters
+    /// narrow the candidates.
+    fn search_content(&mut self, base_path: String) {
+        let Some(sftp_arc) = self.sftp.clone() else {
+            return;
+        };
+        let matcher = match find_regex(&self.content_query, self.content_mode) {
+            Ok(matcher) => matcher,
+            Err(e) => {
+                self.status_msg = format!("Invalid regex: {}", e);
+                return;
+            }
+        };
+        let filter = match self.search_filter.compile() {
+            Ok(filter) => filter,
+            Err(e) => {
+                self.status_msg = e;
+                return;
+            }
+        };
+        let request = GrepRequest {
+            base_path,
+            filter,
+            matcher,
+            rules: self
+                .settings
+                .encoding_rules
+                .iter()
+                .filter(|r| r.host == self.host)
+                .cloned()
+                .collect(),
+        };
+        self.status_msg = format!("Searching contents for '{}'...", self.content_query);
+        let id = self.next_worker_id();
+        self.content_search = Some(ContentSearch::start(
+            sftp_arc,
+            id,
+            self.content_query.clone(),
+            request,
+            self.sender.clone(),
+        ));
+    }
+
+    /// Lists content search hits; clicking one opens the file at that line.
+    fn show_content_search(&mut self, ctx: &egui::Context) {
+        let Some(search) = &mut self.content_search else {
+            return;
+        };
+        let mut is_open = true;
+        let mut open_hit = None;
+        egui::Window::new(format!("Content search: {}", search.query))
+            .open(&mut is_open)
+            .default_size([700.0, 400.0])
+            .resizable(true)
+            .show(ctx, |ui| {
+                ui.horizontal(|ui| {
+                    ui.label(format!(
+                        "{} hits in {} files",
+                        search.hits.len(),
+                        search.files_scanned
+                    ));
+                    if search.running {
+                        ui.spinner();
+                        if ui.button("Stop").clicked() {
+                            search.stop();
+                        }
+                    } else if let Some(limit) = &search.limit {
+                        ui.label(format!("({})", limit));
+                    }
+                });
+                ui.separator();
+
+                let row_height = ui.text_style_height(&egui::TextStyle::Body);
+                egui::ScrollArea::both().auto_shrink(false).show_rows(
+                    ui,
+                    row_height,
+                    search.hits.len(),
+                    |ui, rows| {
+                        for hit in &search.hits[rows] {
+                            ui.horizontal(|ui| {
+                                let location = format!("{}:{}", hit.path, hit.line);
+                                if ui
+                                    .link(location)
+                                    .on_hover_text("Open at this line")
+                                    .clicked()
+                                {
+                                    open_hit = Some(hit.clone());
+                                }
+                                ui.label(egui::RichText::new(hit.text.trim()).monospace());
+                            });
+                        }
+                    },
+                );
             });
+        if !is_open {
+            self.content_search = None;
         }
+        if let Some(hit) = open_hit {
+            self.is_loading = true;
+            self.status_msg = format!("Reading {}...", hit.path);
+            self.view_file(hit.path.clone());
+            self.pending_hit = Some(hit);
+        }
+    }
+
+    /// Lists the directory containing a search result and selects it there.
+    fn reveal_in_folder(&mut self, remote_path: String) {
+        let parent = match remote_path.rsplit_once('/') {
+            Some(("", _)) => "/".to_string(),
+            Some((parent, _)) => parent.to_string(),
+            None => return,
+        };
+        self.pending_reveal = Some(remote_path);
+        self.is_loading = true;
+        self.list_directory(parent);
     }
 
     fn trigger_sort(&mut self, column: SortColumn) {
@@ -243,9 +661,18 @@ impl SshApp {
         self.files.sort_by(|a, b| {
             let ord = match self.sort_column {
                 SortColumn::Permission => a.perm.cmp(&b.perm),
+                SortColumn::Owner => self
+                    .id_names
+                    .user_name(a.uid)
+                    .cmp(&self.id_names.user_name(b.uid)),
+                SortColumn::Group => self
+                    .id_names
+                    .group_name(a.gid)
+                    .cmp(&self.id_names.group_name(b.gid)),
                 SortColumn::Size => a.size.cmp(&b.size),
                 SortColumn::Date => a.date.cmp(&b.date),
                 SortColumn::Name => a.name.cmp(&b.name),
+                SortColumn::Path => a.path.cmp(&b.path),
                 SortColumn::None => std::cmp::Ordering::Equal,
             };
 
@@ -256,22 +683,13 @@ impl SshApp {
         });
     }
 
-    fn download_file(&self, file_name: String, local_path: PathBuf) {
+    fn download_file(&self, remote_path: String, local_path: PathBuf) {
         let session_arc = self.session.clone();
         let tx = self.sender.clone();
-        let current_path = self.current_path.clone();
 
         if let Some(session_arc) = session_arc {
             thread::spawn(move || {
-                let display_name = file_name.clone();
-                let remote_path = if current_path.ends_with('/') {
-                    format!("{}{}", current_path, &file_name)
-                } else if current_path.is_empty() {
-                    file_name
-                } else {
-                    format!("{}/{}", current_path, &file_name)
-                };
-
+                let display_name = remote_path.clone();
                 let result = download_worker(session_arc, &remote_path, local_path);
                 match result {
                     Ok(_) => {
@@ -365,10 +783,10 @@ impl SshApp {
     }
 
     fn load_favorites(&self) -> Vec<crate::model::FavoriteConnection> {
-        if let Ok(file) = std::fs::File::open("favorites.json") {
-            if let Ok(favs) = serde_json::from_reader(file) {
-                return favs;
-            }
+        if let Ok(file) = std::fs::File::open("favorites.json")
+            && let Ok(favs) = serde_json::from_reader(file)
+        {
+            return favs;
         }
         Vec::new()
     }
@@ -420,11 +838,78 @@ impl SshApp {
         }
     }
 
+    fn load_settings(&self) -> AppSettings {
+        if let Ok(file) = std::fs::File::open("settings.json")
+            && let Ok(settings) = serde_json::from_reader(file)
+        {
+            return settings;
+        }
+        AppSettings::default()
+    }
+
+    fn save_settings(&self) {
+        if let Ok(file) = std::fs::File::create("settings.json") {
+            let _ = serde_json::to_writer_pretty(file, &self.settings);
+        }
+    }
+
+    fn remembered_encoding(&self, remote_path: &str) -> Option<FileEncoding> {
+        self.settings
+            .encoding_rules
+            .iter()
+            .find(|r| r.host == self.host && matches_pattern(remote_path, &r.pattern))
+            .map(|r| r.encoding)
+    }
+
+    fn remember_encoding(&mut self, pattern: String, encoding: FileEncoding) {
+        let rules = &mut self.settings.encoding_rules;
+        rules.retain(|r| !(r.host == self.host && r.pattern == pattern));
+        rules.insert(
+            0,
+            EncodingRule {
+                host: self.host.clone(),
+                pattern: pattern.clone(),
+                encoding,
+            },
+        );
+        self.save_settings();
+        self.status_msg = format!("Files matching {} will open as {}.", pattern, encoding);
+    }
+
+    fn edit_externally(&mut self, remote_path: &str) {
+        let remote_path = remote_path.to_string();
+        if self
+            .external_edits
+            .iter()
+            .any(|e| e.remote_path == remote_path)
+        {
+            self.status_msg = format!("{} is already open in the external editor.", remote_path);
+            return;
+        }
+
+        if let Some(sftp_arc) = self.sftp.clone() {
+            self.status_msg = format!("Opening {} in external editor...", remote_path);
+            self.external_edits.push(ExternalEdit::start(
+                sftp_arc,
+                remote_path,
+                self.settings.external_editor.clone(),
+                self.sender.clone(),
+            ));
+        }
+    }
+
+    fn stop_external_edits(&mut self) {
+        for edit in &self.external_edits {
+            edit.stop();
+        }
+        self.external_edits.clear();
+    }
+
     fn load_directory_bookmarks(&self) -> Vec<crate::model::DirectoryBookmark> {
-        if let Ok(file) = std::fs::File::open("directory_bookmarks.json") {
-            if let Ok(bookmarks) = serde_json::from_reader(file) {
-                return bookmarks;
-            }
+        if let Ok(file) = std::fs::File::open("directory_bookmarks.json")
+            && let Ok(bookmarks) = serde_json::from_reader(file)
+        {
+            return bookmarks;
         }
         Vec::new()
     }
@@ -474,312 +959,2778 @@ impl SshApp {
         }
     }
 
+    fn load_saved_searches(&self) -> Vec<SavedSearch> {
+        if let Ok(file) = std::fs::File::open("saved_searches.json")
+            && let Ok(searches) = serde_json::from_reader(file)
+        {
+            return searches;
+        }
+        Vec::new()
+    }
+
+    fn save_saved_searches(&self) {
+        if let Ok(file) = std::fs::File::create("saved_searches.json") {
+            let _ = serde_json::to_writer_pretty(file, &self.saved_searches);
+        }
+    }
+
+    fn load_search_history(&self) -> Vec<SearchQuery> {
+        if let Ok(file) = std::fs::File::open("search_history.json")
+            && let Ok(history) = serde_json::from_reader(file)
+        {
+            return history;
+        }
+        Vec::new()
+    }
+
+    fn save_search_history(&self) {
+        if let Ok(file) = std::fs::File::create("search_history.json") {
+            let _ = serde_json::to_writer_pretty(file, &self.search_history);
+        }
+    }
+
+    fn add_saved_search(&mut self) {
+        if self.saved_search_name_input.is_empty() {
+            self.status_msg = "Search name cannot be empty.".to_owned();
+            return;
+        }
+
+        let new_search = SavedSearch {
+            name: self.saved_search_name_input.clone(),
+            query: self.current_search_query(),
+        };
+
+        // Names are per host, like directory bookmarks shown per host
+        if let Some(pos) = self
+            .saved_searches
+            .iter()
+            .position(|s| s.name == new_search.name && s.query.host == self.host)
+        {
+            self.saved_searches[pos] = new_search;
+            self.status_msg = format!("Updated search '{}'", self.saved_search_name_input);
+        } else {
+            self.saved_searches.push(new_search);
+            self.status_msg = format!("Saved search '{}'", self.saved_search_name_input);
+        }
+        self.save_saved_searches();
+        self.saved_search_name_input.clear();
+    }
+
+    fn delete_saved_search(&mut self) {
+        if self.saved_search_name_input.is_empty() {
+            self.status_msg = "Search name cannot be empty.".to_owned();
+            return;
+        }
+
+        if let Some(pos) = self
+            .saved_searches
+            .iter()
+            .position(|s| s.name == self.saved_search_name_input && s.query.host == self.host)
+        {
+            self.saved_searches.remove(pos);
+            self.save_saved_searches();
+            self.status_msg = format!("Deleted search '{}'", self.saved_search_name_input);
+            self.saved_search_name_input.clear();
+        } else {
+            self.status_msg = format!("Search '{}' not found", self.saved_search_name_input);
+        }
+    }
+
+    /// Fetches the subdirectories of tree nodes, one readdir at a time.
+    fn load_tree_children(&self, paths: Vec<String>) {
+        let Some(sftp_arc) = self.sftp.clone() else {
+            return;
+        };
+        if paths.is_empty() {
+            return;
+        }
+        let tx = self.sender.clone();
+        thread::spawn(move || {
+            for path in paths {
+                let result = list_subdirectories(&sftp_arc, &path).map_err(|e| e.to_string());
+                let _ = tx.send(AppMessage::TreeChildren(path, result));
+            }
+        });
+    }
+
+    /// Expands the tree down to the current directory.
+    fn reveal_in_tree(&mut self) {
+        let to_load = self.dir_tree.reveal(&self.current_path);
+        self.load_tree_children(to_load);
+        self.tree_scroll_to_current = true;
+    }
+
+    /// Switches the address bar from the breadcrumb to a text field.
+    fn edit_path(&mut self) {
+        self.path_input = self.current_path.trim_end_matches('/').to_string() + "/";
+        self.path_editing = true;
+        self.focus_path_input = true;
+        // Directories may have changed since the last edit
+        self.path_completion = PathCompletion::default();
+    }
+
+    /// Fetches the subdirectories of the directory being typed into, once per directory.
+    fn request_path_completions(&mut self) {
+        let Some((dir, _)) = split_input(&self.path_input) else {
+            return;
+        };
+        let Some(sftp_arc) = self.sftp.clone() else {
+            return;
+        };
+        if !self.path_completion.request(&dir) {
+            return;
+        }
+        let tx = self.sender.clone();
+        thread::spawn(move || {
+            let names = list_subdirectories(&sftp_arc, &dir).unwrap_or_default();
+            let _ = tx.send(AppMessage::PathCompletions(dir, names));
+        });
+    }
+
+    fn go_back(&mut self) {
+        if let Some(path) = self.nav_history.back() {
+            self.is_loading = true;
+            self.list_directory(path);
+        }
+    }
+
+    fn go_forward(&mut self) {
+        if let Some(path) = self.nav_history.forward() {
+            self.is_loading = true;
+            self.list_directory(path);
+        }
+    }
+
     fn navigate_to_bookmark(&mut self, bookmark_path: String) {
         println!("Navigating to bookmark: {}", bookmark_path);
         self.is_loading = true;
         self.list_directory(bookmark_path);
     }
 
-    fn view_file(&self, file_name: String) {
+    fn view_file(&mut self, remote_path: String) {
         let sftp_arc = self.sftp.clone();
         let tx = self.sender.clone();
-        let current_path = self.current_path.clone();
+        let spool_id = self.next_worker_id();
 
         if let Some(sftp_arc) = sftp_arc {
             thread::spawn(move || {
-                let remote_path = if current_path.ends_with('/') {
-                    format!("{}{}", current_path, &file_name)
-                } else if current_path.is_empty() {
-                    file_name
-                } else {
-                    format!("{}/{}", current_path, &file_name)
-                };
+                if let Some(compression) = Compression::from_path(&remote_path) {
+                    // The viewer opens on the head while the rest keeps spooling
+                    match decompress_remote(
+                        &sftp_arc,
+                        &remote_path,
+                        compression,
+                        FULL_LOAD_LIMIT,
+                        spool_id,
+                    ) {
+                        Ok((decompressed, writer)) => {
+                            let _ = tx.send(AppMessage::DecompressResult(
+                                remote_path.clone(),
+                                Ok(decompressed),
+                            ));
+                            if let Some(writer) = writer {
+                                writer.run(&remote_path, &tx);
+                            }
+                        }
+                        Err(e) => {
+                            let _ = tx.send(AppMessage::DecompressResult(
+                                remote_path,
+                                Err(e.to_string()),
+                            ));
+                        }
+                    }
+                    return;
+                }
 
-                // Use SFTP API to read file content (max 100KB)
-                if let Err(e) =
-                    crate::ssh::read_file_content(&sftp_arc, &remote_path, 100000, tx.clone())
-                {
+                // Use SFTP API to read file content; larger files continue in the paged viewer.
+                // Images are fetched whole so they can be decoded, but only once the
+                // first bytes confirm one; a mis-named log still pages like text
+                let path = remote_path.clone();
+                let image_limit = move |head: &[u8], size: u64| {
+                    let image = is_image_path(&path) && image_extension(&path, head).is_some();
+                    if image && size <= MAX_IMAGE_BYTES as u64 {
+                        MAX_IMAGE_BYTES
+                    } else {
+                        FULL_LOAD_LIMIT
+                    }
+                };
+                if let Err(e) = crate::ssh::read_file_content(
+                    &sftp_arc,
+                    &remote_path,
+                    FULL_LOAD_LIMIT,
+                    image_limit,
+                    tx.clone(),
+                ) {
                     let _ = tx.send(AppMessage::FileContentResult(Err(e.to_string())));
                 }
             });
         }
     }
 
-    fn show_file_viewer(&mut self, ctx: &egui::Context) {
-        let mut is_open = self.viewing_file.is_some();
-        if is_open {
-            if let Some(state) = &mut self.viewing_file {
-                egui::Window::new(format!("Viewing: {}", state.filename))
-                    .open(&mut is_open)
-                    .default_size([600.0, 400.0])
-                    .show(ctx, |ui| {
-                        ui.horizontal(|ui| {
-                            ui.label("Encoding:");
-                            let previous_encoding = state.encoding;
+    /// Reads pages from the remote file, or from the spool for decompressed files.
+    fn fetch_pages(&self, remote_path: String, pages: Vec<u64>, spool: Option<Arc<SpoolFile>>) {
+        let sftp_arc = self.sftp.clone();
+        let tx = self.sender.clone();
 
-                            egui::ComboBox::from_id_salt("encoding_combo")
-                                .selected_text(format!("{}", state.encoding))
-                                .show_ui(ui, |ui| {
-                                    ui.selectable_value(
-                                        &mut state.encoding,
-                                        FileEncoding::Utf8,
-                                        "UTF-8",
-                                    );
-                                    ui.selectable_value(
-                                        &mut state.encoding,
-                                        FileEncoding::ShiftJis,
-                                        "Shift-JIS",
-                                    );
-                                });
+        if let Some(sftp_arc) = sftp_arc {
+            thread::spawn(move || {
+                for page in pages {
+                    let result = match &spool {
+                        Some(spool) => read_spool_range(spool, page * PAGE_SIZE, PAGE_SIZE),
+                        None => {
+                            read_file_range(&sftp_arc, &remote_path, page * PAGE_SIZE, PAGE_SIZE)
+                        }
+                    }
+                    .map_err(|e| e.to_string());
+                    let _ = tx.send(AppMessage::FileRangeResult(
+                        remote_path.clone(),
+                        page,
+                        result,
+                    ));
+                }
+            });
+        }
+    }
 
-                            if state.encoding != previous_encoding {
-                                // Re-decode on change
-                                let coder = match state.encoding {
-                                    FileEncoding::Utf8 => encoding_rs::UTF_8,
-                                    FileEncoding::ShiftJis => encoding_rs::SHIFT_JIS,
-                                };
-                                let (decoded, _, _) = coder.decode(&state.raw_content);
-                                state.decoded_content = decoded.into_owned();
-                            }
-                        });
-                        ui.separator();
+    fn start_line_index(
+        &self,
+        remote_path: String,
+        cancel: Arc<AtomicBool>,
+        spool: Option<Arc<SpoolFile>>,
+    ) {
+        let sftp_arc = self.sftp.clone();
+        let tx = self.sender.clone();
 
-                        egui::ScrollArea::vertical().show(ui, |ui| {
-                            ui.add(
-                                egui::TextEdit::multiline(&mut state.decoded_content)
-                                    .font(egui::TextStyle::Monospace)
-                                    .desired_width(f32::INFINITY)
-                                    .code_editor(),
-                            );
-                        });
-                    });
-            }
-        }
-        if !is_open {
-            self.viewing_file = None;
+        if let Some(sftp_arc) = sftp_arc {
+            thread::spawn(move || {
+                // Indexing is best effort; the viewer works without line numbers
+                let _ = match spool {
+                    Some(spool) => std::fs::File::open(&spool.path)
+                        .map_err(anyhow::Error::from)
+                        .and_then(|file| build_line_index(file, &remote_path, &cancel, tx)),
+                    None => open_locked(&sftp_arc, &remote_path)
+                        .and_then(|(file, _)| build_line_index(file, &remote_path, &cancel, tx)),
+                };
+            });
         }
     }
 
-    fn show_browser(&mut self, ctx: &egui::Context) {
-        egui::CentralPanel::default().show(ctx, |ui| {
-            ui.horizontal(|ui| {
-                ui.heading("SSH File Browser");
-                if self.is_loading {
-                    ui.spinner();
+    fn load_properties(&self, remote_path: &str) {
+        let sftp_arc = self.sftp.clone();
+        let tx = self.sender.clone();
+        let remote_path = remote_path.to_string();
+
+        if let Some(sftp_arc) = sftp_arc {
+            thread::spawn(move || {
+                if let Err(e) = stat_path(&sftp_arc, &remote_path, tx.clone()) {
+                    let _ = tx.send(AppMessage::PropertiesResult(Err(e.to_string())));
                 }
-                ui.with_layout(egui::Layout::right_to_left(egui::Align::Center), |ui| {
-                    if ui.button("Disconnect").clicked() {
-                        self.is_connected = false;
-                        self.session = None;
-                        self.files.clear();
-                        self.status_msg = "Disconnected.".to_owned();
-                    }
-                });
             });
+        }
+    }
 
-            // Address Bar
-            ui.horizontal(|ui| {
-                if ui
-                    .button("⬆")
-                    .on_hover_text("Go to parent directory")
-                    .clicked()
-                {
-                    let mut new_path = self.current_path.trim_end_matches('/').to_string();
-                    if !new_path.is_empty() {
-                        if let Some(idx) = new_path.rfind('/') {
-                            if idx == 0 {
-                                new_path = "/".to_string();
-                            } else {
-                                new_path.truncate(idx);
-                            }
-                            self.is_loading = true;
-                            self.list_directory(new_path);
-                        }
-                    }
-                }
+    fn open_properties_dialog(&mut self, path: String, stat: FileStat) {
+        let mode = stat.perm.unwrap_or(0) & 0o7777;
+        let is_dir = stat.is_dir();
+        // Files inside a directory default to the directory mode without execute bits
+        let file_mode = if is_dir { mode & !0o111 } else { mode };
 
-                ui.label("Path:");
-                let response = ui.add(
-                    egui::TextEdit::singleline(&mut self.current_path).desired_width(f32::INFINITY),
+        self.properties_dialog = Some(PropertiesDialogState {
+            path,
+            is_dir,
+            dir_mode: mode,
+            dir_mode_input: format_mode_octal(mode),
+            file_mode,
+            file_mode_input: format_mode_octal(file_mode),
+            uid: OwnerField::new(stat.uid),
+            gid: OwnerField::new(stat.gid),
+            recursive: false,
+        });
+    }
+
+    fn apply_properties(&mut self) {
+        let Some(state) = &self.properties_dialog else {
+            return;
+        };
+
+        // Only send ownership the user edited; chown usually needs root
+        let recursive = state.is_dir && state.recursive;
+        let change = match PermissionChange::new(
+            state.dir_mode,
+            state.file_mode,
+            &state.uid,
+            &state.gid,
+            recursive,
+        ) {
+            Ok(change) => change,
+            Err(e) => {
+                self.status_msg = e;
+                return;
+            }
+        };
+        let path = state.path.clone();
+
+        let sftp_arc = self.sftp.clone();
+        let tx = self.sender.clone();
+
+        if let Some(sftp_arc) = sftp_arc {
+            self.is_loading = true;
+            self.status_msg = format!("Updating attributes of {}...", path);
+            thread::spawn(move || {
+                let result = apply_permissions(&sftp_arc, &path, &change)
+                    .map(|(count, limit)| {
+                        format!(
+                            "Updated attributes of {} ({} entries{})",
+                            path,
+                            count,
+                            limit.map(|l| format!("; {}", l)).unwrap_or_default()
+                        )
+                    })
+                    .map_err(|e| e.to_string());
+                let _ = tx.send(AppMessage::PermissionsResult(result));
+            });
+        }
+        self.properties_dialog = None;
+    }
+
+    fn show_properties_dialog(&mut self, ctx: &egui::Context) {
+        let mut is_open = true;
+        let mut apply_clicked = false;
+        let mut cancel_clicked = false;
+
+        if let Some(state) = &mut self.properties_dialog {
+            egui::Window::new(format!("Properties: {}", state.path))
+                .open(&mut is_open)
+                .collapsible(false)
+                .resizable(false)
+                .show(ctx, |ui| {
+                    if state.is_dir {
+                        ui.label("Directory mode:");
+                        permission_editor(
+                            ui,
+                            "props_dir_mode",
+                            &mut state.dir_mode,
+                            &mut state.dir_mode_input,
+                        );
+                        ui.checkbox(&mut state.recursive, "Apply recursively");
+                        if state.recursive {
+                            ui.separator();
+                            ui.label("File mode (files inside):");
+                            permission_editor(
+                                ui,
+                                "props_file_mode",
+                                &mut state.file_mode,
+                                &mut state.file_mode_input,
+                            );
+                        }
+                    } else {
+                        ui.label("Mode:");
+                        permission_editor(
+                            ui,
+                            "props_file_mode",
+                            &mut state.file_mode,
+                            &mut state.file_mode_input,
+                        );
+                    }
+
+                    ui.separator();
+                    egui::Grid::new("props_owner_grid")
+                        .num_columns(2)
+                        .show(ui, |ui| {
+                            ui.label("UID:");
+                            ui.text_edit_singleline(&mut state.uid.input);
+                            ui.end_row();
+
+                            ui.label("GID:");
+                            ui.text_edit_singleline(&mut state.gid.input);
+                            ui.end_row();
+                        });
+
+                    ui.separator();
+                    ui.horizontal(|ui| {
+                        if ui.button("Apply").clicked() {
+                            apply_clicked = true;
+                        }
+                        if ui.button("Cancel").clicked() {
+                            cancel_clicked = true;
+                        }
+                    });
+                });
+        }
+
+        if apply_clicked {
+            self.apply_properties();
+        } else if !is_open || cancel_clicked {
+            self.properties_dialog = None;
+        }
+    }
+
+    fn create_link(&mut self) {
+        let Some(state) = &self.symlink_dialog else {
+            return;
+        };
+        if state.link_name.is_empty() || state.target.is_empty() {
+            self.status_msg = "Link name and target cannot be empty.".to_owned();
+            return;
+        }
+
+        let link_path = join_remote_path(&self.current_path, &state.link_name);
+        let target = state.target.clone();
+        let sftp_arc = self.sftp.clone();
+        let tx = self.sender.clone();
+
+        if let Some(sftp_arc) = sftp_arc {
+            self.is_loading = true;
+            self.status_msg = format!("Creating link {}...", link_path);
+            thread::spawn(move || {
+                let result = create_symlink(&sftp_arc, &link_path, &target)
+                    .map(|_| format!("Created link {} -> {}", link_path, target))
+                    .map_err(|e| e.to_string());
+                let _ = tx.send(AppMessage::SymlinkResult(result));
+            });
+        }
+        self.symlink_dialog = None;
+    }
+
+    fn show_symlink_dialog(&mut self, ctx: &egui::Context) {
+        let mut is_open = true;
+        let mut create_clicked = false;
+        let mut cancel_clicked = false;
+
+        if let Some(state) = &mut self.symlink_dialog {
+            egui::Window::new("New Symlink")
+                .open(&mut is_open)
+                .collapsible(false)
+                .resizable(false)
+                .show(ctx, |ui| {
+                    egui::Grid::new("symlink_grid")
+                        .num_columns(2)
+                        .show(ui, |ui| {
+                            ui.label("Link name:");
+                            ui.text_edit_singleline(&mut state.link_name);
+                            ui.end_row();
+
+                            ui.label("Target:");
+                            ui.text_edit_singleline(&mut state.target);
+                            ui.end_row();
+                        });
+
+                    ui.separator();
+                    ui.horizontal(|ui| {
+                        if ui.button("Create").clicked() {
+                            create_clicked = true;
+                        }
+                        if ui.button("Cancel").clicked() {
+                            cancel_clicked = true;
+                        }
+                    });
+                });
+        }
+
+        if create_clicked {
+            self.create_link();
+        } else if !is_open || cancel_clicked {
+            self.symlink_dialog = None;
+        }
+    }
+
+    fn save_viewed_file(&mut self, force: bool) {
+        let Some(state) = &mut self.viewing_file else {
+            return;
+        };
+        if state.truncated {
+            self.status_msg = "File was only partially loaded; saving is disabled.".to_owned();
+            return;
+        }
+        if let Some(compression) = state.compression {
+            self.status_msg = format!("{} files are read-only.", compression);
+            return;
+        }
+        if !state.has_text {
+            self.status_msg = "Images are shown as a preview only; saving is disabled.".to_owned();
+            return;
+        }
+
+        let text = state.line_ending.apply(&state.decoded_content);
+        let (mut content, had_errors) = state.encoding.encode(&text);
+        if had_errors {
+            self.status_msg = format!(
+                "Text contains characters not representable in {}.",
+                state.encoding
+            );
+            return;
+        }
+
+        if state.bom
+            && let Some(bom) = state.encoding.bom()
+        {
+            content.splice(0..0, bom.iter().copied());
+        }
+        // Later saves would overwrite the backup with an already edited version
+        let original = (!state.backed_up).then(|| state.raw_content.clone());
+        let path = state.filename.clone();
+        let expected_mtime = if force { None } else { state.mtime };
+        let sftp_arc = self.sftp.clone();
+        let tx = self.sender.clone();
+
+        if let Some(sftp_arc) = sftp_arc {
+            state.is_saving = true;
+            state.conflict = false;
+            self.status_msg = format!("Saving {}...", path);
+            thread::spawn(move || {
+                match write_file_content(
+                    &sftp_arc,
+                    &path,
+                    &content,
+                    original.as_deref(),
+                    expected_mtime,
+                ) {
+                    Ok(mtime) => {
+                        let _ = tx.send(AppMessage::SaveResult(path, Ok((content, mtime))));
+                    }
+                    Err(e) if e.is::<RemoteModifiedError>() => {
+                        let _ = tx.send(AppMessage::SaveConflict(path));
+                    }
+                    Err(e) => {
+                        let _ = tx.send(AppMessage::SaveResult(path, Err(e.to_string())));
+                    }
+                }
+            });
+        }
+    }
+
+    fn show_file_viewer(&mut self, ctx: &egui::Context) {
+        let mut is_open = self.viewing_file.is_some();
+        let mut save_requested = None;
+        let mut notice = None;
+        let mut follow_toggled = None;
+        let mut remember_rule = None;
+        if is_open && let Some(state) = &mut self.viewing_file {
+            egui::Window::new(format!("Viewing: {}", state.filename))
+                .open(&mut is_open)
+                .default_size([600.0, 400.0])
+                .show(ctx, |ui| {
+                    ui.horizontal(|ui| {
+                        ui.label("Encoding:");
+                        let previous_encoding = state.encoding;
+
+                        egui::ComboBox::from_id_salt("encoding_combo")
+                            .selected_text(format!("{}", state.encoding))
+                            .height(300.0)
+                            .show_ui(ui, |ui| {
+                                for &encoding in FileEncoding::ALL {
+                                    ui.selectable_value(
+                                        &mut state.encoding,
+                                        encoding,
+                                        encoding.to_string(),
+                                    );
+                                }
+                            });
+                        if ui
+                            .button("Remember...")
+                            .on_hover_text("Always open matching files with this encoding")
+                            .clicked()
+                        {
+                            state.remember_pattern = match state.remember_pattern {
+                                Some(_) => None,
+                                None => Some(default_encoding_pattern(&state.filename)),
+                            };
+                        }
+
+                        if state.encoding != previous_encoding && state.has_text {
+                            // Re-decode on change (discards unsaved edits)
+                            (state.decoded_content, state.line_ending) =
+                                decode_for_editing(&state.raw_content, state.encoding);
+                            state.revision += 1;
+                            state.modified = false;
+                            if let Some(follow) = &mut state.follow {
+                                follow.decode(state.encoding);
+                            }
+                        }
+
+                        let editable = state.paged.is_none()
+                            && state.compression.is_none()
+                            && state.follow.is_none()
+                            && state.view_mode == ViewMode::Text;
+                        let bom_label = if state.bom { " + BOM" } else { "" };
+                        if editable {
+                            let previous_ending = state.line_ending;
+                            egui::ComboBox::from_id_salt("line_ending_combo")
+                                .selected_text(state.line_ending.to_string())
+                                .show_ui(ui, |ui| {
+                                    for ending in LineEnding::CONVERTIBLE {
+                                        ui.selectable_value(
+                                            &mut state.line_ending,
+                                            ending,
+                                            ending.to_string(),
+                                        );
+                                    }
+                                });
+                            if state.line_ending != previous_ending {
+                                if previous_ending == LineEnding::Mixed {
+                                    state.decoded_content =
+                                        LineEnding::normalize(&state.decoded_content);
+                                    state.revision += 1;
+                                }
+                                state.modified = true;
+                            }
+                            if ui
+                                .add_enabled(
+                                    state.encoding.bom().is_some(),
+                                    egui::Checkbox::new(&mut state.bom, "BOM"),
+                                )
+                                .on_disabled_hover_text("Only Unicode encodings have a BOM")
+                                .changed()
+                            {
+                                state.modified = true;
+                            }
+
+                            ui.separator();
+                            egui::ComboBox::from_id_salt("syntax_combo")
+                                .selected_text(state.syntax.as_deref().unwrap_or("Plain text"))
+                                .height(300.0)
+                                .show_ui(ui, |ui| {
+                                    ui.selectable_value(&mut state.syntax, None, "Plain text");
+                                    for name in syntax_names() {
+                                        ui.selectable_value(
+                                            &mut state.syntax,
+                                            Some(name.clone()),
+                                            name,
+                                        );
+                                    }
+                                });
+                            if state.syntax.is_some()
+                                && state.decoded_content.len() > MAX_HIGHLIGHT_BYTES
+                            {
+                                ui.label("(too large to highlight)");
+                            }
+                        } else {
+                            ui.label(format!("{}{}", state.line_ending, bom_label));
+                        }
+
+                        ui.separator();
+                        if state.has_text {
+                            ui.selectable_value(&mut state.view_mode, ViewMode::Text, "Text");
+                        }
+                        ui.selectable_value(&mut state.view_mode, ViewMode::Hex, "Hex");
+                        if state.image.is_some() {
+                            ui.selectable_value(&mut state.view_mode, ViewMode::Image, "Image");
+                        }
+                        // Structured views need the whole text
+                        if state.paged.is_none() && state.follow.is_none() {
+                            state.data.update(
+                                &state.filename,
+                                &state.decoded_content,
+                                state.revision,
+                            );
+                            if state.data.json.is_some() {
+                                ui.selectable_value(&mut state.view_mode, ViewMode::Json, "Tree");
+                            }
+                            if state.data.table.is_some() {
+                                ui.selectable_value(&mut state.view_mode, ViewMode::Table, "Table");
+                            }
+                        }
+
+                        // Appended compressed data cannot be decoded on its own
+                        if state.compression.is_none() {
+                            ui.separator();
+                            let mut following = state.follow.is_some();
+                            if ui
+                                .toggle_value(&mut following, "Follow")
+                                .on_hover_text("Stream appended data like tail -f")
+                                .changed()
+                            {
+                                follow_toggled = Some(following);
+                            }
+                        }
+                        if state.follow.is_some() || state.view_mode != ViewMode::Text {
+                            return;
+                        }
+
+                        if let Some(compression) = state.compression {
+                            ui.separator();
+                            ui.label(format!("Read-only ({} decompressed)", compression));
+                            if state.spooling.is_some() {
+                                ui.spinner();
+                            } else if state.spool_capped {
+                                ui.colored_label(
+                                    egui::Color32::YELLOW,
+                                    format!("Truncated at {} GB", MAX_SPOOL_BYTES >> 30),
+                                );
+                            }
+                            return;
+                        }
+
+                        if state.paged.is_some() {
+                            ui.separator();
+                            ui.label("Read-only (large file)");
+                            return;
+                        }
+
+                        ui.separator();
+                        let can_save = state.modified && !state.truncated && !state.is_saving;
+                        if ui
+                            .add_enabled(can_save, egui::Button::new("Save"))
+                            .on_disabled_hover_text(if state.truncated {
+                                "Only part of the file was loaded"
+                            } else {
+                                "No changes to save"
+                            })
+                            .clicked()
+                        {
+                            save_requested = Some(false);
+                        }
+                        if state.is_saving {
+                            ui.spinner();
+                        } else if state.modified {
+                            ui.label("(modified)");
+                        }
+                    });
+
+                    let mut close_remember = false;
+                    if let Some(pattern) = &mut state.remember_pattern {
+                        ui.horizontal(|ui| {
+                            ui.label(format!("Use {} for paths matching", state.encoding));
+                            ui.add(egui::TextEdit::singleline(pattern).desired_width(200.0));
+                            if ui.button("Save rule").clicked() {
+                                remember_rule = Some((pattern.clone(), state.encoding));
+                                close_remember = true;
+                            }
+                            if ui.button("Cancel").clicked() {
+                                close_remember = true;
+                            }
+                        });
+                    }
+                    if close_remember {
+                        state.remember_pattern = None;
+                    }
+
+                    if state.conflict {
+                        ui.horizontal(|ui| {
+                            ui.label(
+                                egui::RichText::new("Remote file changed since it was loaded.")
+                                    .color(egui::Color32::RED),
+                            );
+                            if ui.button("Save anyway").clicked() {
+                                save_requested = Some(true);
+                            }
+                        });
+                    }
+                    ui.separator();
+
+                    if let Some(follow) = &mut state.follow {
+                        show_follow_content(ui, follow);
+                        return;
+                    }
+
+                    if state.view_mode == ViewMode::Image
+                        && let Some(image) = &mut state.image
+                    {
+                        show_image_content(ui, image);
+                        return;
+                    }
+
+                    if state.view_mode == ViewMode::Json
+                        && let Some(json) = &state.data.json
+                    {
+                        show_json_content(ui, json);
+                        return;
+                    }
+
+                    if state.view_mode == ViewMode::Table && state.data.table.is_some() {
+                        show_table_content(ui, &mut state.data);
+                        return;
+                    }
+
+                    if state.view_mode == ViewMode::Hex {
+                        notice = show_hex_content(
+                            ui,
+                            &state.raw_content,
+                            &mut state.hex,
+                            state.truncated,
+                        );
+                        return;
+                    }
+
+                    if let Some(paged) = &mut state.paged {
+                        notice = show_paged_content(
+                            ui,
+                            paged,
+                            state.encoding,
+                            &mut state.goto_input,
+                            &mut state.find,
+                        );
+                        return;
+                    }
+
+                    let mut jump = None;
+                    ui.horizontal(|ui| {
+                        ui.label("Find:");
+                        let response = ui.add(
+                            egui::TextEdit::singleline(&mut state.find.query)
+                                .desired_width(200.0)
+                                .hint_text("Enter: next, Shift+Enter: previous"),
+                        );
+                        egui::ComboBox::from_id_salt("find_mode_combo")
+                            .selected_text(state.find.mode.to_string())
+                            .show_ui(ui, |ui| {
+                                for mode in [FindMode::Plain, FindMode::IgnoreCase, FindMode::Regex]
+                                {
+                                    ui.selectable_value(
+                                        &mut state.find.mode,
+                                        mode,
+                                        mode.to_string(),
+                                    );
+                                }
+                            });
+                        state.find.update(&state.decoded_content, state.revision);
+
+                        let (enter, shift) =
+                            ui.input(|i| (i.key_pressed(egui::Key::Enter), i.modifiers.shift));
+                        let submitted = response.lost_focus() && enter;
+                        if ui.button("Prev").clicked() || (submitted && shift) {
+                            jump = state.find.step(false);
+                        }
+                        if ui.button("Next").clicked() || (submitted && !shift) {
+                            jump = state.find.step(true);
+                        }
+                        if submitted {
+                            // Keep typing Enter to walk through matches
+                            response.request_focus();
+                        }
+                        if let Some(e) = &state.find.error {
+                            ui.colored_label(egui::Color32::RED, "Invalid regex")
+                                .on_hover_text(e);
+                        } else if !state.find.query.is_empty() {
+                            match state.find.current {
+                                Some(i) => {
+                                    ui.label(format!("{} / {}", i + 1, state.find.matches.len()))
+                                }
+                                None => ui.label(format!("{} matches", state.find.matches.len())),
+                            };
+                        }
+
+                        ui.separator();
+                        ui.label("Line:");
+                        let response = ui.add(
+                            egui::TextEdit::singleline(&mut state.line_input).desired_width(60.0),
+                        );
+                        if ui.button("Go").clicked() || (response.lost_focus() && enter) {
+                            match state
+                                .line_input
+                                .trim()
+                                .parse()
+                                .ok()
+                                .and_then(|line| line_start(&state.decoded_content, line))
+                            {
+                                Some(start) => jump = Some(start..start),
+                                None => {
+                                    notice = Some(format!("No line {}", state.line_input.trim()))
+                                }
+                            }
+                        }
+                    });
+
+                    if let Some(line) = state.pending_line.take()
+                        && let Some(start) = line_start(&state.decoded_content, line as usize)
+                    {
+                        let text = &state.decoded_content;
+                        let end = text[start..].find('\n').map_or(text.len(), |i| start + i);
+                        jump = Some(start..end);
+                    }
+
+                    let syntax = state
+                        .syntax
+                        .clone()
+                        .filter(|_| state.decoded_content.len() <= MAX_HIGHLIGHT_BYTES);
+                    let cache = &mut state.highlight_cache;
+                    let matches = &state.find.matches;
+                    let current_match = state.find.current;
+                    // Shared with the edit buffer, which bumps it on every keystroke
+                    let revision = std::cell::Cell::from_mut(&mut state.revision);
+                    let mut layouter = |ui: &egui::Ui, text: &str, wrap_width: f32| {
+                        let mut job = match &syntax {
+                            Some(syntax) => cache.layout(ui, text, syntax, revision.get()),
+                            None => egui::text::LayoutJob::simple(
+                                text.to_owned(),
+                                egui::TextStyle::Monospace.resolve(ui.style()),
+                                ui.visuals().text_color(),
+                                wrap_width,
+                            ),
+                        };
+                        highlight_matches(
+                            &mut job,
+                            matches,
+                            current_match,
+                            egui::Color32::from_rgba_unmultiplied(255, 200, 0, 60),
+                            egui::Color32::from_rgba_unmultiplied(255, 140, 0, 160),
+                        );
+                        job.wrap.max_width = wrap_width;
+                        ui.fonts(|f| f.layout_job(job))
+                    };
+
+                    // Move the cursor to the jump target (byte offsets -> char offsets)
+                    let text_id = ui.make_persistent_id("viewer_text");
+                    let jump_chars = jump.and_then(|range| {
+                        let text = &state.decoded_content;
+                        let start = text.get(..range.start)?.chars().count();
+                        let len = text.get(range)?.chars().count();
+                        Some((start, start + len))
+                    });
+                    if let Some((start, end)) = jump_chars {
+                        let mut text_state =
+                            egui::TextEdit::load_state(ui.ctx(), text_id).unwrap_or_default();
+                        text_state.cursor.set_char_range(Some(
+                            egui::text_selection::CCursorRange::two(
+                                egui::text::CCursor::new(start),
+                                egui::text::CCursor::new(end),
+                            ),
+                        ));
+                        text_state.store(ui.ctx(), text_id);
+                    }
+
+                    let line_count = state.decoded_content.lines().count().max(1);
+                    egui::ScrollArea::vertical().show(ui, |ui| {
+                        ui.horizontal_top(|ui| {
+                            let font_id = egui::TextStyle::Monospace.resolve(ui.style());
+                            let char_width = ui.fonts(|f| f.glyph_width(&font_id, '0'));
+                            let digits = line_count.to_string().len();
+                            let (gutter, _) = ui.allocate_exact_size(
+                                egui::vec2((digits as f32 + 1.0) * char_width, 0.0),
+                                egui::Sense::hover(),
+                            );
+
+                            let mut buffer = EditedText {
+                                text: &mut state.decoded_content,
+                                revision,
+                            };
+                            let output = egui::TextEdit::multiline(&mut buffer)
+                                .id(text_id)
+                                .font(egui::TextStyle::Monospace)
+                                .desired_width(f32::INFINITY)
+                                .code_editor()
+                                .layouter(&mut layouter)
+                                .show(ui);
+                            if output.response.changed() {
+                                state.modified = true;
+                            }
+
+                            // Line numbers on the first row of each (possibly wrapped) line
+                            let painter = ui.painter();
+                            let clip = ui.clip_rect();
+                            let color = ui.visuals().weak_text_color();
+                            let mut line = 1;
+                            let mut starts_line = true;
+                            for row in &output.galley.rows {
+                                let y = output.galley_pos.y + row.rect.top();
+                                if starts_line
+                                    && y + row.rect.height() >= clip.top()
+                                    && y <= clip.bottom()
+                                {
+                                    painter.text(
+                                        egui::pos2(gutter.right() - char_width * 0.5, y),
+                                        egui::Align2::RIGHT_TOP,
+                                        line.to_string(),
+                                        font_id.clone(),
+                                        color,
+                                    );
+                                }
+                                starts_line = row.ends_with_newline;
+                                if starts_line {
+                                    line += 1;
+                                }
+                            }
+
+                            if let Some((start, _)) = jump_chars {
+                                let rect = output
+                                    .galley
+                                    .pos_from_ccursor(egui::text::CCursor::new(start));
+                                ui.scroll_to_rect(
+                                    rect.translate(output.galley_pos.to_vec2()),
+                                    Some(egui::Align::Center),
+                                );
+                            }
+                        });
+                    });
+                });
+        }
+        if !is_open {
+            if let Some(image) = self.viewing_file.take().and_then(|state| state.image) {
+                ctx.forget_image(&image.uri);
+            }
+        } else if let Some(force) = save_requested {
+            self.save_viewed_file(force);
+        }
+        if let Some(msg) = notice {
+            self.status_msg = msg;
+        }
+        if let Some((pattern, encoding)) = remember_rule {
+            self.remember_encoding(pattern, encoding);
+        }
+        if let Some(enable) = follow_toggled {
+            self.set_following(enable);
+        }
+
+        // Fetch pages the paged viewer is waiting for
+        if let Some(state) = &mut self.viewing_file
+            && let Some(paged) = &mut state.paged
+        {
+            let pages = paged.take_requests();
+            if !pages.is_empty() {
+                let path = state.filename.clone();
+                let spool = state.spool.clone();
+                self.fetch_pages(path, pages, spool);
+            }
+        }
+    }
+
+    /// Opens the viewer on loaded content. `size` is the full (decompressed)
+    /// size; content beyond `raw_content` is paged in from the remote file or
+    /// the spool.
+    fn open_viewer(
+        &mut self,
+        ctx: &egui::Context,
+        name: String,
+        raw_content: Vec<u8>,
+        size: Option<u64>,
+        mtime: Option<u64>,
+        compressed: Option<(Compression, Option<Arc<SpoolFile>>, Option<Spooling>)>,
+    ) {
+        // A remembered rule wins over detection
+        let encoding = self
+            .remembered_encoding(&name)
+            .unwrap_or_else(|| FileEncoding::detect(&raw_content));
+        let bom = encoding_rs::Encoding::for_bom(&raw_content).is_some();
+        // Compressed files are recognized by the name without the extension
+        let (compression, spool, spooling) = match compressed {
+            Some((compression, spool, spooling)) => (Some(compression), spool, spooling),
+            None => (None, None, None),
+        };
+        let inner_name = match compression {
+            Some(_) => name
+                .rsplit_once('.')
+                .map_or(name.as_str(), |(stem, _)| stem),
+            None => name.as_str(),
+        };
+        let truncated = size.is_some_and(|size| size > raw_content.len() as u64);
+        let image_type = image_extension(inner_name, &raw_content);
+        let oversized_image = truncated && image_type.is_some();
+        let image = image_type
+            .filter(|_| !truncated)
+            .map(|ext| ImageViewState::new(inner_name, ext, &raw_content));
+        // Large images are only fetched whole for the preview; don't put them in the editor
+        let has_text = image.is_none() || raw_content.len() <= FULL_LOAD_LIMIT;
+        let (decoded_string, line_ending) = if has_text {
+            decode_for_editing(&raw_content, encoding)
+        } else {
+            (String::new(), LineEnding::Lf)
+        };
+        let syntax = detect_syntax(inner_name, &decoded_string);
+        // Binary content opens in the hex dump instead of garbled text
+        let utf16 = matches!(encoding, FileEncoding::Utf16Le | FileEncoding::Utf16Be);
+        let view_mode = if image.is_some() {
+            ViewMode::Image
+        } else if is_binary(&raw_content) && !utf16 {
+            ViewMode::Hex
+        } else {
+            ViewMode::Text
+        };
+        if let Some(old) = &self.viewing_file
+            && let Some(old_image) = &old.image
+        {
+            ctx.forget_image(&old_image.uri);
+        }
+        let mut paged = truncated.then(|| PagedFile::new(size.unwrap_or(0), &raw_content));
+        // Closing the viewer drops `paged`, which also stops the spool writer
+        if let (Some(paged), Some(spooling)) = (&mut paged, &spooling) {
+            paged.cancel = spooling.cancel.clone();
+        }
+        // Bring the content search hit this file was opened from into view
+        let hit = self.pending_hit.take().filter(|hit| hit.path == name);
+        if let (Some(hit), Some(paged)) = (&hit, &mut paged) {
+            paged.jump_to_offset(hit.offset);
+        }
+        // A spool is indexed once it is complete
+        if let Some(paged) = &paged
+            && spooling.is_none()
+        {
+            self.start_line_index(name.clone(), paged.cancel.clone(), spool.clone());
+        }
+        self.viewing_file = Some(FileViewerState {
+            filename: name,
+            raw_content,
+            decoded_content: decoded_string,
+            has_text,
+            revision: 0,
+            encoding,
+            line_ending,
+            bom,
+            mtime,
+            truncated,
+            modified: false,
+            is_saving: false,
+            conflict: false,
+            backed_up: false,
+            paged,
+            goto_input: String::new(),
+            follow: None,
+            view_mode,
+            hex: HexViewState::default(),
+            remember_pattern: None,
+            syntax,
+            highlight_cache: HighlightCache::default(),
+            find: FindState::default(),
+            line_input: String::new(),
+            image,
+            compression,
+            spool,
+            spooling: spooling.map(|spooling| spooling.id),
+            spool_capped: false,
+            data: DataViewState::default(),
+            pending_line: hit.filter(|_| !truncated).map(|hit| hit.line),
+        });
+        self.status_msg = if oversized_image {
+            format!(
+                "Image is larger than {} MB; showing it as data.",
+                MAX_IMAGE_BYTES / (1024 * 1024)
+            )
+        } else {
+            "File content loaded.".to_owned()
+        };
+    }
+
+    fn set_following(&mut self, enable: bool) {
+        let id = self.next_worker_id();
+        let (Some(sftp_arc), Some(state)) = (self.sftp.clone(), &mut self.viewing_file) else {
+            return;
+        };
+        state.follow = enable
+            .then(|| FileFollow::start(sftp_arc, id, state.filename.clone(), self.sender.clone()));
+    }
+
+    fn show_tree_panel(&mut self, ctx: &egui::Context) {
+        let current = match self.current_path.trim_end_matches('/') {
+            "" => "/",
+            path => path,
+        }
+        .to_string();
+        let mut go_to = None;
+        let mut toggle = None;
+        let mut reload = false;
+        let mut locate = false;
+        egui::SidePanel::left("tree_panel")
+            .resizable(true)
+            .default_width(220.0)
+            .show(ctx, |ui| {
+                ui.horizontal(|ui| {
+                    ui.heading("Folders");
+                    if ui
+                        .small_button("⟳")
+                        .on_hover_text("Reload folders")
+                        .clicked()
+                    {
+                        reload = true;
+                    }
+                    if ui
+                        .small_button("◎")
+                        .on_hover_text("Locate the current folder")
+                        .clicked()
+                    {
+                        locate = true;
+                    }
+                });
+                ui.separator();
+
+                egui::ScrollArea::both()
+                    .auto_shrink([false, false])
+                    .show(ui, |ui| {
+                        for row in self.dir_tree.rows() {
+                            match row {
+                                TreeRow::Dir {
+                                    path,
+                                    name,
+                                    depth,
+                                    expanded,
+                                    leaf,
+                                } => {
+                                    ui.horizontal(|ui| {
+                                        ui.add_space(depth as f32 * 12.0);
+                                        if leaf {
+                                            ui.add_space(14.0);
+                                        } else {
+                                            let arrow = if expanded { "⏷" } else { "⏵" };
+                                            if ui
+                                                .add(egui::Button::new(arrow).frame(false))
+                                                .clicked()
+                                            {
+                                                toggle = Some(path.clone());
+                                            }
+                                        }
+                                        let is_current = path == current;
+                                        let response = ui.selectable_label(is_current, name);
+                                        if is_current && self.tree_scroll_to_current {
+                                            response.scroll_to_me(Some(egui::Align::Center));
+                                            self.tree_scroll_to_current = false;
+                                        }
+                                        if response.clicked() {
+                                            go_to = Some(path);
+                                        }
+                                    });
+                                }
+                                TreeRow::Note { depth, text } => {
+                                    ui.horizontal(|ui| {
+                                        ui.add_space(depth as f32 * 12.0 + 14.0);
+                                        ui.weak(text);
+                                    });
+                                }
+                            }
+                        }
+                    });
+            });
+
+        if reload {
+            let to_load = self.dir_tree.reload();
+            self.load_tree_children(to_load);
+        }
+        if locate {
+            self.reveal_in_tree();
+        }
+        if let Some(path) = toggle
+            && self.dir_tree.toggle(&path)
+        {
+            self.load_tree_children(vec![path]);
+        }
+        if let Some(path) = go_to {
+            self.is_loading = true;
+            self.list_directory(path);
+        }
+    }
+
+    fn show_details_panel(&mut self, ctx: &egui::Context) {
+        egui::SidePanel::right("details_panel")
+            .resizable(true)
+            .default_width(280.0)
+            .show(ctx, |ui| {
+                ui.heading("Details");
+                ui.separator();
+
+                let Some(file) = &self.selected_file else {
+                    ui.label("No file selected.");
+                    return;
+                };
+
+                let file_type = match file.perm.chars().next() {
+                    Some('d') => "Directory",
+                    Some('l') => "Symbolic link",
+                    Some('p') => "Named pipe",
+                    Some('c') => "Character device",
+                    Some('b') => "Block device",
+                    Some('s') => "Socket",
+                    _ => "Regular file",
+                };
+                let mode = file
+                    .mode
+                    .map(|m| format!("{} ({})", file.perm, format_mode_octal(m)))
+                    .unwrap_or_else(|| file.perm.clone());
+                let with_id = |name: String, id: Option<u32>| match id {
+                    Some(id) => format!("{} ({})", name, id),
+                    None => name,
+                };
+
+                egui::Grid::new("details_grid")
+                    .num_columns(2)
+                    .striped(true)
+                    .show(ui, |ui| {
+                        ui.label("Name:");
+                        ui.label(&file.name);
+                        ui.end_row();
+
+                        ui.label("Path:");
+                        ui.label(&file.path);
+                        ui.end_row();
+
+                        ui.label("Type:");
+                        ui.label(file_type);
+                        ui.end_row();
+
+                        if let Some(link) = &file.link {
+                            ui.label("Link target:");
+                            if link.broken {
+                                ui.label(
+                                    egui::RichText::new(format!("{} (broken)", link.target))
+                                        .color(egui::Color32::RED),
+                                );
+                            } else {
+                                ui.label(&link.target);
+                            }
+                            ui.end_row();
+                        }
+
+                        ui.label("Mode:");
+                        ui.label(mode);
+                        ui.end_row();
+
+                        ui.label("Raw mode:");
+                        ui.label(file.mode.map(|m| format!("{:o}", m)).unwrap_or_default());
+                        ui.end_row();
+
+                        ui.label("Size:");
+                        ui.label(format!("{} bytes", file.size));
+                        ui.end_row();
+
+                        ui.label("Owner:");
+                        ui.label(with_id(self.id_names.user_name(file.uid), file.uid));
+                        ui.end_row();
+
+                        ui.label("Group:");
+                        ui.label(with_id(self.id_names.group_name(file.gid), file.gid));
+                        ui.end_row();
+
+                        ui.label("Modified:");
+                        ui.label(format_timestamp_full(file.mtime));
+                        ui.end_row();
+
+                        ui.label("Accessed:");
+                        ui.label(format_timestamp_full(file.atime));
+                        ui.end_row();
+                    });
+            });
+    }
+
+    /// Path field with directory suggestions below it. Tab completes like a
+    /// shell; returns the path to list once Enter is pressed.
+    fn show_path_input(&mut self, ui: &mut egui::Ui) -> Option<String> {
+        let ctx = ui.ctx().clone();
+        let input_id = egui::Id::new("path_input");
+        let popup_id = input_id.with("suggestions");
+        let tab = ctx.memory(|m| m.has_focus(input_id))
+            && ctx.input_mut(|i| i.consume_key(egui::Modifiers::NONE, egui::Key::Tab));
+
+        let response = ui.add(
+            egui::TextEdit::singleline(&mut self.path_input)
+                .id(input_id)
+                .lock_focus(true)
+                .desired_width(ui.available_width() - 40.0),
+        );
+        if std::mem::take(&mut self.focus_path_input) {
+            response.request_focus();
+        }
+        self.request_path_completions();
+
+        let mut completed = None;
+        if tab && response.has_focus() {
+            completed = self.path_completion.complete(&self.path_input);
+        }
+        if response.lost_focus() {
+            let (enter, escape) = ctx.input(|i| {
+                (
+                    i.key_pressed(egui::Key::Enter),
+                    i.key_pressed(egui::Key::Escape),
+                )
+            });
+            if enter {
+                return Some(self.path_input.clone());
+            }
+            // Clicking a suggestion takes focus away without ending the edit
+            let over_popup = ctx
+                .memory(|m| m.area_rect(popup_id))
+                .zip(ctx.pointer_interact_pos())
+                .is_some_and(|(rect, pos)| rect.contains(pos));
+            if escape || !over_popup {
+                self.path_editing = false;
+                return None;
+            }
+        }
+
+        let prefix = split_input(&self.path_input).map_or("", |(_, prefix)| prefix);
+        let suggestions: Vec<String> = self
+            .path_completion
+            .matches(prefix)
+            .take(MAX_SUGGESTIONS)
+            .cloned()
+            .collect();
+        if !suggestions.is_empty() {
+            egui::Area::new(popup_id)
+                .order(egui::Order::Foreground)
+                .fixed_pos(response.rect.left_bottom())
+                .show(&ctx, |ui| {
+                    egui::Frame::popup(ui.style()).show(ui, |ui| {
+                        for name in &suggestions {
+                            if ui.selectable_label(false, name).clicked() {
+                                completed = self.path_completion.pick(name);
+                            }
+                        }
+                    });
+                });
+        }
+
+        if let Some(path) = completed {
+            self.path_input = path;
+            response.request_focus();
+            // Keep typing after the completed text
+            if let Some(mut state) = egui::text_edit::TextEditState::load(&ctx, input_id) {
+                let end = egui::text::CCursor::new(self.path_input.chars().count());
+                state
+                    .cursor
+                    .set_char_range(Some(egui::text::CCursorRange::one(end)));
+                state.store(&ctx, input_id);
+            }
+        }
+        None
+    }
+
+    fn show_browser(&mut self, ctx: &egui::Context) {
+        // Mouse back/forward buttons, and Alt+arrows unless a text field has focus
+        let typing = ctx.wants_keyboard_input();
+        let (back, forward) = ctx.input(|i| {
+            let key = |key| !typing && i.modifiers.alt && i.key_pressed(key);
+            (
+                i.pointer.button_pressed(egui::PointerButton::Extra1) || key(egui::Key::ArrowLeft),
+                i.pointer.button_pressed(egui::PointerButton::Extra2) || key(egui::Key::ArrowRight),
+            )
+        });
+        if back {
+            self.go_back();
+        } else if forward {
+            self.go_forward();
+        }
+        if ctx.input(|i| i.modifiers.command && i.key_pressed(egui::Key::L)) {
+            self.edit_path();
+        }
+
+        egui::CentralPanel::default().show(ctx, |ui| {
+            ui.horizontal(|ui| {
+                ui.heading("SSH File Browser");
+                if self.is_loading {
+                    ui.spinner();
+                }
+                ui.with_layout(egui::Layout::right_to_left(egui::Align::Center), |ui| {
+                    if ui.button("Disconnect").clicked() {
+                        self.is_connected = false;
+                        self.stop_external_edits();
+                        // Workers of this session must not feed the next one
+                        if let Some(search) = self.name_search.take() {
+                            search.cancel.store(true, Ordering::SeqCst);
+                        }
+                        self.content_search = None;
+                        if let Some(state) = &mut self.viewing_file {
+                            state.follow = None;
+                        }
+                        self.search_base = None;
+                        self.is_loading = false;
+                        self.session = None;
+                        self.id_names = IdNames::default();
+                        self.nav_history.clear();
+                        self.dir_tree.clear();
+                        self.files.clear();
+                        self.status_msg = "Disconnected.".to_owned();
+                    }
+                });
+            });
+
+            // Address Bar
+            ui.horizontal(|ui| {
+                let back_tip = self
+                    .nav_history
+                    .back_entries()
+                    .take(10)
+                    .fold("Back (Alt+Left)".to_string(), |tip, path| tip + "\n" + path);
+                if ui
+                    .add_enabled(self.nav_history.can_go_back(), egui::Button::new("⬅"))
+                    .on_hover_text(back_tip)
+                    .clicked()
+                {
+                    self.go_back();
+                }
+                if ui
+                    .add_enabled(self.nav_history.can_go_forward(), egui::Button::new("➡"))
+                    .on_hover_text("Forward (Alt+Right)")
+                    .clicked()
+                {
+                    self.go_forward();
+                }
+                let mut recent_path = None;
+                ui.menu_button("Recent", |ui| {
+                    for path in self.nav_history.recent() {
+                        if ui.button(path).clicked() {
+                            recent_path = Some(path.clone());
+                            ui.close_menu();
+                        }
+                    }
+                })
+                .response
+                .on_hover_text("Recently visited directories");
+                if let Some(path) = recent_path {
+                    self.is_loading = true;
+                    self.list_directory(path);
+                }
+                if ui
+                    .button("⬆")
+                    .on_hover_text("Go to parent directory")
+                    .clicked()
+                {
+                    let mut new_path = self.current_path.trim_end_matches('/').to_string();
+                    if !new_path.is_empty()
+                        && let Some(idx) = new_path.rfind('/')
+                    {
+                        if idx == 0 {
+                            new_path = "/".to_string();
+                        } else {
+                            new_path.truncate(idx);
+                        }
+                        self.is_loading = true;
+                        self.list_directory(new_path);
+                    }
+                }
+
+                ui.label("Path:");
+                let mut go_to = None;
+                if self.path_editing {
+                    go_to = self.show_path_input(ui);
+                    if ui.button("Go").clicked() {
+                        go_to = Some(self.path_input.clone());
+                    }
+                } else {
+                    egui::ScrollArea::horizontal()
+                        .id_salt("breadcrumb_scroll")
+                        .max_width(ui.available_width() - 60.0)
+                        .stick_to_right(true)
+                        .show(ui, |ui| {
+                            ui.horizontal(|ui| {
+                                let crumbs = breadcrumbs(&self.current_path);
+                                let last = crumbs.len() - 1;
+                                for (i, (label, path)) in crumbs.into_iter().enumerate() {
+                                    if i > 1 {
+                                        ui.label("›");
+                                    }
+                                    if ui.selectable_label(i == last, label).clicked() {
+                                        go_to = Some(path);
+                                    }
+                                }
+                            });
+                        });
+                    if ui
+                        .button("✏")
+                        .on_hover_text("Type a path (Ctrl+L)")
+                        .clicked()
+                    {
+                        self.edit_path();
+                    }
+                    if ui.button("Go").on_hover_text("Reload").clicked() {
+                        go_to = Some(self.current_path.clone());
+                    }
+                }
+                if let Some(path) = go_to {
+                    self.path_editing = false;
+                    self.is_loading = true;
+                    self.list_directory(path);
+                }
+            });
+
+            // Bookmarks Bar
+            ui.horizontal(|ui| {
+                ui.label("Bookmarks:");
+                egui::ScrollArea::horizontal().show(ui, |ui| {
+                    ui.horizontal(|ui| {
+                        // Filter bookmarks for current host
+                        let current_host_bookmarks: Vec<_> = self.directory_bookmarks
+                            .iter()
+                            .filter(|b| b.host == self.host)
+                            .collect();
+
+                        if current_host_bookmarks.is_empty() {
+                            ui.label("(No bookmarks for this host)");
+                        } else {
+                            let mut path_to_navigate: Option<String> = None;
+                            for bookmark in current_host_bookmarks {
+                                if ui.button(&bookmark.name).clicked() {
+                                    println!("Bookmark clicked: {} -> {}", bookmark.name, bookmark.path);
+                                    path_to_navigate = Some(bookmark.path.clone());
+                                    break; // Only handle one click per frame
+                                }
+                            }
+                            // Navigate after the loop to avoid borrow checker issues
+                            if let Some(path) = path_to_navigate {
+                                self.navigate_to_bookmark(path);
+                            }
+                        }
+                    });
+                });
+            });
+
+            // Bookmark Management
+            ui.horizontal(|ui| {
+                ui.label("Bookmark:");
+                ui.text_edit_singleline(&mut self.bookmark_name_input);
+                if ui.button("Add").clicked() {
+                    self.add_directory_bookmark();
+                }
+                if ui.button("Delete").clicked() {
+                    self.delete_directory_bookmark();
+                }
+            });
+
+            // Search Bar
+            ui.horizontal(|ui| {
+                ui.label("Search:");
+                let response = ui.add(
+                    egui::TextEdit::singleline(&mut self.search_filter.pattern).hint_text(
+                        if self.search_filter.regex {
+                            "Filename regex (e.g. ^app\\.log\\.\\d+$)"
+                        } else {
+                            "Filename pattern (e.g. *.txt)"
+                        },
+                    ),
                 );
-                if response.lost_focus() && ctx.input(|i| i.key_pressed(egui::Key::Enter)) {
+                ui.checkbox(&mut self.search_filter.regex, "Regex");
+                ui.checkbox(&mut self.search_filter.ignore_case, "Ignore case");
+                ui.label("Containing:");
+                let content_response = ui.add(
+                    egui::TextEdit::singleline(&mut self.content_query)
+                        .hint_text("Text in files (optional)"),
+                );
+                egui::ComboBox::from_id_salt("content_mode_combo")
+                    .selected_text(self.content_mode.to_string())
+                    .show_ui(ui, |ui| {
+                        for mode in [FindMode::Plain, FindMode::IgnoreCase, FindMode::Regex] {
+                            ui.selectable_value(&mut self.content_mode, mode, mode.to_string());
+                        }
+                    });
+                ui.checkbox(&mut self.search_filter.recursive, "Recursive");
+
+                let submitted = (response.lost_focus() || content_response.lost_focus())
+                    && ctx.input(|i| i.key_pressed(egui::Key::Enter));
+                if let Some(search) = &self.name_search {
+                    // One file name search at a time; results stream into the list
+                    if search.cancel.load(Ordering::SeqCst) {
+                        ui.label("Stopping...");
+                    } else if ui.button("Stop").clicked() {
+                        search.cancel.store(true, Ordering::SeqCst);
+                    }
+                } else if ui.button("Search").clicked() || submitted {
+                    self.run_search(self.current_path.clone());
+                }
+            });
+            egui::CollapsingHeader::new("Search filters").show(ui, |ui| {
+                let filter = &mut self.search_filter;
+                ui.horizontal_wrapped(|ui| {
+                    ui.label("Size:");
+                    ui.add(
+                        egui::TextEdit::singleline(&mut filter.min_size)
+                            .hint_text("min (e.g. 10K)")
+                            .desired_width(80.0),
+                    );
+                    ui.label("–");
+                    ui.add(
+                        egui::TextEdit::singleline(&mut filter.max_size)
+                            .hint_text("max (e.g. 1.5G)")
+                            .desired_width(80.0),
+                    );
+                    ui.separator();
+                    ui.label("Modified:");
+                    ui.add(
+                        egui::TextEdit::singleline(&mut filter.modified_after)
+                            .hint_text("after YYYY-MM-DD")
+                            .desired_width(120.0),
+                    );
+                    ui.label("–");
+                    ui.add(
+                        egui::TextEdit::singleline(&mut filter.modified_before)
+                            .hint_text("before YYYY-MM-DD")
+                            .desired_width(120.0),
+                    );
+                    ui.separator();
+                    egui::ComboBox::from_id_salt("search_type_combo")
+                        .selected_text(filter.entry_type.to_string())
+                        .show_ui(ui, |ui| {
+                            for entry_type in EntryType::ALL {
+                                ui.selectable_value(
+                                    &mut filter.entry_type,
+                                    entry_type,
+                                    entry_type.to_string(),
+                                );
+                            }
+                        });
+                    ui.separator();
+                    ui.add_enabled_ui(filter.recursive, |ui| {
+                        ui.label("Max depth:");
+                        ui.add(
+                            egui::TextEdit::singleline(&mut filter.max_depth)
+                                .hint_text("any")
+                                .desired_width(40.0),
+                        );
+                        ui.checkbox(&mut filter.follow_links, "Follow symlinked directories")
+                            .on_hover_text(
+                                "Loops are detected and each directory is searched once",
+                            );
+                        ui.checkbox(&mut filter.one_filesystem, "Stay on one filesystem")
+                            .on_hover_text("Skip other mounts such as /proc or network shares");
+                    });
+                    ui.separator();
+                    ui.label("Exclude:");
+                    ui.add(
+                        egui::TextEdit::singleline(&mut filter.exclude)
+                            .hint_text(".git, node_modules")
+                            .desired_width(160.0),
+                    );
+                });
+            });
+
+            // Saved Searches
+            ui.horizontal(|ui| {
+                ui.label("Saved searches:");
+                let mut to_run = None;
+                ui.menu_button("History", |ui| {
+                    let recent: Vec<_> = self
+                        .search_history
+                        .iter()
+                        .filter(|q| q.host == self.host)
+                        .collect();
+                    if recent.is_empty() {
+                        ui.label("(No recent searches for this host)");
+                    }
+                    for query in recent {
+                        if ui.button(query.label()).clicked() {
+                            to_run = Some(query.clone());
+                            ui.close_menu();
+                        }
+                    }
+                });
+                egui::ScrollArea::horizontal()
+                    .id_salt("saved_searches_scroll")
+                    .show(ui, |ui| {
+                        ui.horizontal(|ui| {
+                            let host_searches: Vec<_> = self
+                                .saved_searches
+                                .iter()
+                                .filter(|s| s.query.host == self.host)
+                                .collect();
+                            if host_searches.is_empty() {
+                                ui.label("(None for this host)");
+                            }
+                            for saved in host_searches {
+                                if ui
+                                    .button(&saved.name)
+                                    .on_hover_text(saved.query.label())
+                                    .clicked()
+                                {
+                                    to_run = Some(saved.query.clone());
+                                }
+                            }
+                        });
+                    });
+                // Run after the lists above are no longer borrowed
+                if let Some(query) = to_run {
+                    self.rerun_search(query);
+                }
+            });
+
+            // Saved Search Management
+            ui.horizontal(|ui| {
+                ui.label("Save search as:");
+                ui.text_edit_singleline(&mut self.saved_search_name_input);
+                if ui
+                    .button("Save")
+                    .on_hover_text("Save the search bar and current directory under this name")
+                    .clicked()
+                {
+                    self.add_saved_search();
+                }
+                if ui.button("Delete").clicked() {
+                    self.delete_saved_search();
+                }
+            });
+
+            // Settings
+            egui::CollapsingHeader::new("Settings").show(ui, |ui| {
+                ui.horizontal(|ui| {
+                    ui.label("External editor:");
+                    let response = ui.add(
+                        egui::TextEdit::singleline(&mut self.settings.external_editor)
+                            .hint_text("e.g. code --wait"),
+                    );
+                    if response.lost_focus() {
+                        self.save_settings();
+                    }
+                });
+
+                if ui
+                    .checkbox(
+                        &mut self.settings.server_side_find,
+                        "Search with server-side find when available",
+                    )
+                    .changed()
+                {
+                    self.save_settings();
+                }
+
+                ui.label("Encoding rules:");
+                if self.settings.encoding_rules.is_empty() {
+                    ui.label("(none; use \"Remember...\" in the file viewer)");
+                }
+                let mut remove_rule = None;
+                for (i, rule) in self.settings.encoding_rules.iter().enumerate() {
+                    ui.horizontal(|ui| {
+                        ui.label(format!(
+                            "{}  {}  →  {}",
+                            rule.host, rule.pattern, rule.encoding
+                        ));
+                        if ui.small_button("Remove").clicked() {
+                            remove_rule = Some(i);
+                        }
+                    });
+                }
+                if let Some(i) = remove_rule {
+                    self.settings.encoding_rules.remove(i);
+                    self.save_settings();
+                }
+            });
+
+            ui.label(&self.status_msg);
+            ui.separator();
+
+            // Action Bar
+            ui.horizontal(|ui| {
+                if ui.button("Refresh").clicked() {
                     self.is_loading = true;
                     self.list_directory(self.current_path.clone());
                 }
+                if ui.toggle_value(&mut self.show_tree, "Tree").changed()
+                    && self.show_tree
+                    && self.dir_tree.first_show()
+                {
+                    self.reveal_in_tree();
+                }
+                ui.toggle_value(&mut self.show_details, "Details");
+                if ui.button("New Symlink").clicked() {
+                    // Pre-fill the target with the selected entry
+                    let target = self
+                        .selected_file
+                        .as_ref()
+                        .map(|f| f.path.clone())
+                        .unwrap_or_default();
+                    self.symlink_dialog = Some(SymlinkDialogState {
+                        link_name: String::new(),
+                        target,
+                    });
+                }
 
-                if ui.button("Go").clicked() {
+                let mut edit_request = None;
+                let mut view_request = None;
+                let mut reveal_request = None;
+                if let Some(file) = &self.selected_file {
+                    if !file.is_dir() && ui.button("Edit externally").clicked() {
+                        edit_request = Some(file.path.clone());
+                    }
+                    if ui.button("View").clicked() {
+                        view_request = Some(file.path.clone());
+                    }
+                    if ui.button("Download").clicked()
+                        && let Some(path) =
+                            rfd::FileDialog::new().set_file_name(&file.name).save_file()
+                    {
+                        self.is_loading = true;
+                        self.status_msg = format!("Downloading {}...", file.path);
+                        self.download_file(file.path.clone(), path);
+                    }
+                    if ui.button("Properties").clicked() {
+                        self.load_properties(&file.path);
+                    }
+                    if self.search_base.is_some()
+                        && ui
+                            .button("Reveal in folder")
+                            .on_hover_text("Open the folder containing this result")
+                            .clicked()
+                    {
+                        reveal_request = Some(file.path.clone());
+                    }
+                }
+                if let Some(path) = edit_request {
+                    self.edit_externally(&path);
+                }
+                if let Some(path) = view_request {
                     self.is_loading = true;
-                    self.list_directory(self.current_path.clone());
+                    self.status_msg = format!("Reading {}...", path);
+                    self.view_file(path);
+                }
+                if let Some(path) = reveal_request {
+                    self.reveal_in_folder(path);
                 }
             });
 
-            // Bookmarks Bar
-            ui.horizontal(|ui| {
-                ui.label("Bookmarks:");
-                egui::ScrollArea::horizontal().show(ui, |ui| {
-                    ui.horizontal(|ui| {
-                        // Filter bookmarks for current host
-                        let current_host_bookmarks: Vec<_> = self.directory_bookmarks
-                            .iter()
-                            .filter(|b| b.host == self.host)
-                            .collect();
-                        
-                        if current_host_bookmarks.is_empty() {
-                            ui.label("(No bookmarks for this host)");
-                        } else {
-                            let mut path_to_navigate: Option<String> = None;
-                            for bookmark in current_host_bookmarks {
-                                if ui.button(&bookmark.name).clicked() {
-                                    println!("Bookmark clicked: {} -> {}", bookmark.name, bookmark.path);
-                                    path_to_navigate = Some(bookmark.path.clone());
-                                    break; // Only handle one click per frame
-                                }
+            // External Edits
+            let mut stop_index = None;
+            for (index, edit) in self.external_edits.iter_mut().enumerate() {
+                ui.horizontal(|ui| {
+                    ui.label(format!("Editing externally: {}", edit.remote_path))
+                        .on_hover_text(edit.local_path.display().to_string());
+                    if edit.conflict {
+                        ui.label(
+                            egui::RichText::new("Remote file changed").color(egui::Color32::RED),
+                        );
+                        if ui.button("Upload anyway").clicked() {
+                            edit.upload_anyway();
+                        }
+                    }
+                    if ui.button("Stop").clicked() {
+                        stop_index = Some(index);
+                    }
+                });
+            }
+            if let Some(index) = stop_index {
+                self.external_edits.remove(index).stop();
+            }
+
+            ui.separator();
+
+            // File Table
+            let text_height = egui::TextStyle::Body.resolve(ui.style()).size + 5.0; // slightly taller rows
+
+            // Search results get a column with their path below the search directory
+            let search_base = self.search_base.clone();
+            let mut table = TableBuilder::new(ui)
+                .striped(true)
+                .resizable(true)
+                .cell_layout(egui::Layout::left_to_right(egui::Align::Center))
+                .column(Column::auto())
+                .column(Column::auto())
+                .column(Column::auto())
+                .column(Column::auto())
+                .column(Column::auto());
+            if search_base.is_some() {
+                table = table.column(Column::auto().clip(true));
+            }
+            if std::mem::take(&mut self.scroll_to_selected)
+                && let Some(selected) = &self.selected_file
+                && let Some(row) = self.files.iter().position(|f| f.path == selected.path)
+            {
+                table = table.scroll_to_row(row, Some(egui::Align::Center));
+            }
+            table
+                .column(Column::remainder())
+                .header(20.0, |mut header| {
+                    header.col(|ui| {
+                        if ui.button("Permissions").clicked() {
+                            self.trigger_sort(SortColumn::Permission);
+                        }
+                    });
+                    header.col(|ui| {
+                        if ui.button("Owner").clicked() {
+                            self.trigger_sort(SortColumn::Owner);
+                        }
+                    });
+                    header.col(|ui| {
+                        if ui.button("Group").clicked() {
+                            self.trigger_sort(SortColumn::Group);
+                        }
+                    });
+                    header.col(|ui| {
+                        if ui.button("Size").clicked() {
+                            self.trigger_sort(SortColumn::Size);
+                        }
+                    });
+                    header.col(|ui| {
+                        if ui.button("Date").clicked() {
+                            self.trigger_sort(SortColumn::Date);
+                        }
+                    });
+                    if search_base.is_some() {
+                        header.col(|ui| {
+                            if ui.button("Path").clicked() {
+                                self.trigger_sort(SortColumn::Path);
                             }
-                            // Navigate after the loop to avoid borrow checker issues
-                            if let Some(path) = path_to_navigate {
-                                self.navigate_to_bookmark(path);
+                        });
+                    }
+                    header.col(|ui| {
+                        if ui.button("Name").clicked() {
+                            self.trigger_sort(SortColumn::Name);
+                        }
+                    });
+                })
+                .body(|body| {
+                    body.rows(text_height, self.files.len(), |mut row| {
+                        let row_index = row.index();
+                        let file = self.files[row_index].clone();
+                        let is_selected = self
+                            .selected_file
+                            .as_ref()
+                            .is_some_and(|f| f.path == file.path);
+
+                        row.col(|ui| {
+                            ui.label(&file.perm);
+                        });
+                        row.col(|ui| {
+                            ui.label(self.id_names.user_name(file.uid));
+                        });
+                        row.col(|ui| {
+                            ui.label(self.id_names.group_name(file.gid));
+                        });
+                        row.col(|ui| {
+                            ui.label(file.size.to_string());
+                        });
+                        row.col(|ui| {
+                            ui.label(&file.date);
+                        });
+                        if let Some(base) = &search_base {
+                            row.col(|ui| {
+                                ui.label(relative_path(base, &file.path))
+                                    .on_hover_text(&file.path);
+                            });
+                        }
+                        row.col(|ui| {
+                            let text = match &file.link {
+                                Some(link) => format!("{} -> {}", file.name, link.target),
+                                None => file.name.clone(),
+                            };
+                            let mut label = ui.selectable_label(is_selected, text);
+                            if file.link.as_ref().is_some_and(|l| l.broken) {
+                                ui.label(egui::RichText::new("(broken)").color(egui::Color32::RED));
+                                label = label.on_hover_text("Link target does not exist");
                             }
+                            if label.clicked() {
+                                self.selected_file = Some(file.clone());
+                            }
+                            if label.double_clicked() {
+                                // Navigate into directories and links that resolve to one
+                                if file.is_dir() {
+                                    self.is_loading = true;
+                                    self.list_directory(file.path.clone());
+                                }
+                            }
+                        });
+                    });
+                });
+        });
+    }
+}
+
+/// `path` below `base` without the leading separator, or `path` itself if it
+/// lies elsewhere.
+fn relative_path<'a>(base: &str, path: &'a str) -> &'a str {
+    match path.strip_prefix(base) {
+        Some(rest) if base.ends_with('/') || rest.starts_with('/') => rest.trim_start_matches('/'),
+        _ => path,
+    }
+}
+
+/// Joins a remote directory and an entry name the same way the browser builds paths.
+fn join_remote_path(dir: &str, name: &str) -> String {
+    if dir.ends_with('/') {
+        format!("{}{}", dir, name)
+    } else if dir.is_empty() {
+        name.to_string()
+    } else {
+        format!("{}/{}", dir, name)
+    }
+}
+
+/// Live tail view. Sticks to the bottom until the user scrolls up, which pauses it.
+fn show_follow_content(ui: &mut egui::Ui, follow: &mut FileFollow) {
+    let mut resume = false;
+    ui.horizontal(|ui| {
+        if follow.stopped {
+            ui.colored_label(egui::Color32::RED, "Stopped");
+        } else if follow.paused {
+            ui.label(format!("Paused ({} new bytes)", follow.unseen_bytes));
+            resume = ui.button("Resume").clicked();
+        } else {
+            ui.spinner();
+            ui.label("Following");
+        }
+        if follow.dropped_bytes > 0 {
+            ui.separator();
+            ui.label(format!("{} older bytes discarded", follow.dropped_bytes));
+        }
+    });
+    ui.separator();
+
+    let mut scroll = egui::ScrollArea::vertical()
+        .auto_shrink(false)
+        .stick_to_bottom(true);
+    if resume {
+        scroll = scroll.vertical_scroll_offset(f32::MAX);
+    }
+    let output = scroll.show(ui, |ui| {
+        ui.label(egui::RichText::new(&follow.text).monospace());
+    });
+
+    let bottom = output.state.offset.y + output.inner_rect.height();
+    follow.paused = bottom + 1.0 < output.content_size.y;
+    if !follow.paused {
+        follow.unseen_bytes = 0;
+    }
+    if !follow.stopped {
+        // Messages are only drained on repaint
+        ui.ctx().request_repaint_after(Duration::from_secs(1));
+    }
+}
+
+/// Image preview with fit-to-window, fixed zoom levels and Ctrl+wheel zoom.
+fn show_image_content(ui: &mut egui::Ui, image: &mut ImageViewState) {
+    ui.horizontal(|ui| {
+        if ui.selectable_label(image.zoom.is_none(), "Fit").clicked() {
+            image.zoom = None;
+        }
+        if ui
+            .selectable_label(image.zoom == Some(1.0), "100%")
+            .clicked()
+        {
+            image.zoom = Some(1.0);
+        }
+        if ui.button("-").clicked() {
+            image.zoom_by(0.8);
+        }
+        if ui.button("+").clicked() {
+            image.zoom_by(1.25);
+        }
+        if let Some(zoom) = image.zoom {
+            ui.label(format!("{:.0}%", zoom * 100.0));
+        }
+        if let Some((width, height)) = image.size {
+            ui.separator();
+            ui.label(format!("{} x {} px", width, height));
+        }
+    });
+    ui.separator();
+
+    let source = egui::ImageSource::Bytes {
+        uri: image.uri.clone().into(),
+        bytes: image.bytes.clone(),
+    };
+    let response = match image.zoom {
+        None => ui.add(
+            egui::Image::new(source)
+                .max_size(ui.available_size())
+                .shrink_to_fit(),
+        ),
+        Some(zoom) => {
+            egui::ScrollArea::both()
+                .auto_shrink(false)
+                .show(ui, |ui| {
+                    ui.add(egui::Image::new(source).fit_to_original_size(zoom))
+                })
+                .inner
+        }
+    };
+
+    if response.hovered() {
+        let zoom_delta = ui.input(|i| i.zoom_delta());
+        if zoom_delta != 1.0 {
+            image.zoom_by(zoom_delta);
+        }
+    }
+}
+
+/// Collapsible tree of a JSON document, with one root per record for JSON Lines.
+fn show_json_content(ui: &mut egui::Ui, json: &Result<JsonDocument, String>) {
+    let doc = match json {
+        Ok(doc) => doc,
+        Err(e) => {
+            ui.colored_label(ui.visuals().error_fg_color, format!("Invalid JSON: {}", e));
+            return;
+        }
+    };
+    ui.horizontal(|ui| {
+        if doc.lines {
+            ui.label(format!("JSON Lines: {} records", doc.values.len()));
+            ui.separator();
+        }
+        if ui
+            .button("Copy pretty-printed")
+            .on_hover_text("Copy the document indented")
+            .clicked()
+        {
+            let pretty: Vec<String> = doc
+                .values
+                .iter()
+                .filter_map(|v| serde_json::to_string_pretty(v).ok())
+                .collect();
+            ui.ctx().copy_text(pretty.join("\n"));
+        }
+    });
+    ui.separator();
+
+    egui::ScrollArea::both().auto_shrink(false).show(ui, |ui| {
+        let root = ui.make_persistent_id("json_tree");
+        if doc.lines {
+            for (i, value) in doc.values.iter().enumerate() {
+                json_tree(ui, format!("#{}", i + 1), value, root.with(i), false);
+            }
+        } else {
+            json_tree(ui, "(root)".to_owned(), &doc.values[0], root, true);
+        }
+    });
+}
+
+/// One node of the JSON tree. Containers are collapsible; `open` sets their
+/// initial state.
+fn json_tree(ui: &mut egui::Ui, key: String, value: &serde_json::Value, id: egui::Id, open: bool) {
+    use serde_json::Value;
+
+    let children: Vec<(String, &Value)> = match value {
+        Value::Object(map) => map.iter().map(|(k, v)| (k.clone(), v)).collect(),
+        Value::Array(items) => items
+            .iter()
+            .enumerate()
+            .map(|(i, v)| (format!("[{}]", i), v))
+            .collect(),
+        _ => {
+            let (text, color) = match value {
+                Value::String(s) => (
+                    format!("{:?}", s),
+                    egui::Color32::from_rgb(0x6A, 0xA8, 0x4F),
+                ),
+                Value::Number(n) => (n.to_string(), egui::Color32::from_rgb(0x4F, 0x8F, 0xD8)),
+                Value::Bool(b) => (b.to_string(), egui::Color32::from_rgb(0xC7, 0x7D, 0xBA)),
+                _ => ("null".to_owned(), ui.visuals().weak_text_color()),
+            };
+            ui.horizontal(|ui| {
+                ui.label(egui::RichText::new(format!("{}:", key)).monospace());
+                ui.label(egui::RichText::new(text).monospace().color(color));
+            });
+            return;
+        }
+    };
+
+    let summary = match value {
+        Value::Object(_) => format!("{}: {{{} keys}}", key, children.len()),
+        _ => format!("{}: [{} items]", key, children.len()),
+    };
+    egui::CollapsingHeader::new(egui::RichText::new(summary).monospace())
+        .id_salt(id)
+        .default_open(open)
+        .show(ui, |ui| {
+            for (child_key, child) in children.iter().take(MAX_TREE_CHILDREN) {
+                json_tree(ui, child_key.clone(), child, id.with(child_key), false);
+            }
+            if children.len() > MAX_TREE_CHILDREN {
+                ui.label(format!(
+                    "... {} more not shown",
+                    children.len() - MAX_TREE_CHILDREN
+                ));
+            }
+        });
+}
+
+fn show_table_content(ui: &mut egui::Ui, data: &mut DataViewState) {
+    let table = match &data.table {
+        Some(Ok(table)) => table,
+        Some(Err(e)) => {
+            ui.colored_label(ui.visuals().error_fg_color, format!("Invalid table: {}", e));
+            return;
+        }
+        None => return,
+    };
+
+    let mut has_header = data.has_header;
+    let mut sort_column = None;
+    ui.horizontal(|ui| {
+        ui.label(format!("Delimiter: {}", delimiter_name(table.delimiter)));
+        ui.separator();
+        ui.checkbox(&mut has_header, "Header row");
+        ui.separator();
+        ui.label(format!("{} rows", data.order.len()));
+        ui.label("(click a column name to sort)");
+    });
+    ui.separator();
+
+    let header = data.header();
+    egui::ScrollArea::horizontal().show(ui, |ui| {
+        let row_height = ui.text_style_height(&egui::TextStyle::Body) + 4.0;
+        TableBuilder::new(ui)
+            .id_salt("data_table")
+            .striped(true)
+            .resizable(true)
+            .cell_layout(egui::Layout::left_to_right(egui::Align::Center))
+            .columns(Column::auto().at_least(40.0).clip(true), table.columns)
+            .header(row_height, |mut row| {
+                for column in 0..table.columns {
+                    row.col(|ui| {
+                        let name = header
+                            .and_then(|h| h.get(column))
+                            .cloned()
+                            .unwrap_or_else(|| format!("Column {}", column + 1));
+                        let arrow = match data.sort {
+                            Some((c, true)) if c == column => " ^",
+                            Some((c, false)) if c == column => " v",
+                            _ => "",
+                        };
+                        if ui
+                            .button(egui::RichText::new(format!("{}{}", name, arrow)).strong())
+                            .clicked()
+                        {
+                            sort_column = Some(column);
                         }
                     });
+                }
+            })
+            .body(|body| {
+                body.rows(row_height, data.order.len(), |mut row| {
+                    let cells = &table.rows[data.order[row.index()]];
+                    for column in 0..table.columns {
+                        row.col(|ui| {
+                            ui.label(cells.get(column).map_or("", String::as_str));
+                        });
+                    }
                 });
             });
+    });
+
+    if has_header != data.has_header {
+        data.set_header(has_header);
+    }
+    if let Some(column) = sort_column {
+        data.sort_by(column);
+    }
+}
+
+/// Hex dump with offset, hex and text columns. Returns a message for the status bar.
+fn show_hex_content(
+    ui: &mut egui::Ui,
+    data: &[u8],
+    hex: &mut HexViewState,
+    truncated: bool,
+) -> Option<String> {
+    let mut notice = None;
+    ui.horizontal(|ui| {
+        ui.label("Find hex:");
+        let response = ui.add(
+            egui::TextEdit::singleline(&mut hex.search_input)
+                .desired_width(160.0)
+                .hint_text("7f 45 4c 46"),
+        );
+        let submitted = response.lost_focus() && ui.input(|i| i.key_pressed(egui::Key::Enter));
+        if (ui.button("Find next").clicked() || submitted) && !hex.find_next(data) {
+            notice = Some(match parse_hex_pattern(&hex.search_input) {
+                Some(_) => "Pattern not found.".to_owned(),
+                None => format!("Invalid hex pattern: {}", hex.search_input),
+            });
+        }
+
+        if let Some((start, end)) = hex.selected_range() {
+            ui.separator();
+            ui.label(format!(
+                "Selected 0x{:X}-0x{:X} ({} bytes)",
+                start,
+                end,
+                end - start + 1
+            ));
+            if ui.button("Copy hex").clicked() {
+                ui.ctx().copy_text(format_hex_bytes(&data[start..=end]));
+            }
+        }
+        if truncated {
+            ui.separator();
+            ui.label(format!("First {} bytes only", data.len()));
+        }
+    });
+    ui.separator();
+
+    let row_height = ui.text_style_height(&egui::TextStyle::Monospace);
+    let total_rows = data.len().div_ceil(BYTES_PER_ROW);
+    let extend = ui.input(|i| i.modifiers.shift);
+    let mut scroll = egui::ScrollArea::vertical().auto_shrink(false);
+    if let Some(row) = hex.scroll_to_row.take() {
+        scroll =
+            scroll.vertical_scroll_offset(row as f32 * (row_height + ui.spacing().item_spacing.y));
+    }
+
+    scroll.show_rows(ui, row_height, total_rows, |ui, rows| {
+        for row in rows {
+            let start = row * BYTES_PER_ROW;
+            let bytes = &data[start..(start + BYTES_PER_ROW).min(data.len())];
+            ui.horizontal(|ui| {
+                ui.spacing_mut().item_spacing.x = 0.0;
+                ui.label(
+                    egui::RichText::new(format!("{:08X}  ", start))
+                        .monospace()
+                        .weak(),
+                );
+                for i in 0..BYTES_PER_ROW {
+                    match bytes.get(i) {
+                        Some(b) => hex_cell(ui, format!("{:02X}", b), start + i, hex, extend),
+                        None => {
+                            ui.monospace("  ");
+                        }
+                    }
+                    // Extra gap in the middle of the row
+                    ui.monospace(if i == BYTES_PER_ROW / 2 - 1 {
+                        "  "
+                    } else {
+                        " "
+                    });
+                }
+                ui.monospace(" ");
+                for (i, &b) in bytes.iter().enumerate() {
+                    hex_cell(ui, printable_char(b).to_string(), start + i, hex, extend);
+                }
+            });
+        }
+    });
+
+    notice
+}
+
+/// One clickable byte in the hex or text column.
+fn hex_cell(ui: &mut egui::Ui, text: String, offset: usize, hex: &mut HexViewState, extend: bool) {
+    let mut rich = egui::RichText::new(text).monospace();
+    if hex.is_selected(offset) {
+        rich = rich.background_color(ui.visuals().selection.bg_fill);
+    }
+    if ui
+        .add(egui::Label::new(rich).sense(egui::Sense::click()))
+        .clicked()
+    {
+        hex.click(offset, extend);
+    }
+}
+
+/// Editor text that bumps the viewer's revision on every change, so caches
+/// keyed on it never have to compare the text itself.
+struct EditedText<'a> {
+    text: &'a mut String,
+    revision: &'a std::cell::Cell<u64>,
+}
+
+impl egui::TextBuffer for EditedText<'_> {
+    fn is_mutable(&self) -> bool {
+        true
+    }
+
+    fn as_str(&self) -> &str {
+        self.text
+    }
+
+    fn insert_text(&mut self, text: &str, char_index: usize) -> usize {
+        self.revision.set(self.revision.get() + 1);
+        self.text.insert_text(text, char_index)
+    }
+
+    fn delete_char_range(&mut self, char_range: std::ops::Range<usize>) {
+        self.revision.set(self.revision.get() + 1);
+        self.text.delete_char_range(char_range);
+    }
+}
+
+/// Decodes file content for the editor, normalizing line breaks to "\n"
+/// unless the file mixes styles.
+fn decode_for_editing(raw: &[u8], encoding: FileEncoding) -> (String, LineEnding) {
+    let (decoded, _, _) = encoding.coder().decode(raw);
+    let line_ending = LineEnding::detect(&decoded);
+    let text = match line_ending {
+        LineEnding::Mixed => decoded.into_owned(),
+        _ => LineEnding::normalize(&decoded),
+    };
+    (text, line_ending)
+}
+
+/// Suggested rule pattern: same directory and extension, or the exact path.
+fn default_encoding_pattern(remote_path: &str) -> String {
+    let (dir, name) = remote_path.rsplit_once('/').unwrap_or(("", remote_path));
+    match name.rsplit_once('.') {
+        Some((stem, ext)) if !stem.is_empty() => format!("{}/*.{}", dir, ext),
+        _ => remote_path.to_string(),
+    }
+}
 
-            // Bookmark Management
-            ui.horizontal(|ui| {
-                ui.label("Bookmark:");
-                ui.text_edit_singleline(&mut self.bookmark_name_input);
-                if ui.button("Add").clicked() {
-                    self.add_directory_bookmark();
-                }
-                if ui.button("Delete").clicked() {
-                    self.delete_directory_bookmark();
+/// Paged, read-only view over a large file. Returns a message for the status bar.
+fn show_paged_content(
+    ui: &mut egui::Ui,
+    paged: &mut PagedFile,
+    encoding: FileEncoding,
+    goto_input: &mut String,
+    find: &mut FindState,
+) -> Option<String> {
+    let mut notice = None;
+    let row_height = ui.text_style_height(&egui::TextStyle::Monospace);
+    // Leave room for the navigation and find bars
+    let visible_rows = ((ui.available_height() - row_height * 6.0) / row_height).max(1.0) as usize;
+
+    ui.horizontal(|ui| {
+        ui.label("Go to:");
+        ui.add(
+            egui::TextEdit::singleline(goto_input)
+                .desired_width(100.0)
+                .hint_text("line / offset"),
+        );
+        if ui.button("Line").clicked() {
+            match goto_input.trim().parse::<u64>() {
+                Ok(line) if paged.go_to_line(line) => {}
+                Ok(line) => notice = Some(format!("Line {} is not indexed yet.", line)),
+                Err(_) => notice = Some(format!("Invalid line number: {}", goto_input)),
+            }
+        }
+        if ui
+            .button("Offset")
+            .on_hover_text("Decimal or 0x-prefixed hex")
+            .clicked()
+        {
+            match parse_offset(goto_input) {
+                Some(offset) => paged.jump_to_offset(offset),
+                None => notice = Some(format!("Invalid offset: {}", goto_input)),
+            }
+        }
+        if ui.button("Top").clicked() {
+            paged.jump_to_offset(0);
+        }
+        if ui.button("End").clicked() {
+            paged.jump_to_end(visible_rows);
+        }
+
+        ui.separator();
+        match paged.total_lines {
+            Some(lines) => ui.label(format!("{} lines, {} bytes", lines, paged.size)),
+            None => ui.label(format!(
+                "Indexing lines... {}%",
+                paged.indexed_bytes * 100 / paged.size.max(1)
+            )),
+        };
+    });
+    ui.horizontal(|ui| {
+        ui.label("Find:");
+        let response = ui.add(
+            egui::TextEdit::singleline(&mut find.query)
+                .desired_width(200.0)
+                .hint_text("Enter: next, Shift+Enter: previous"),
+        );
+        egui::ComboBox::from_id_salt("paged_find_mode_combo")
+            .selected_text(find.mode.to_string())
+            .show_ui(ui, |ui| {
+                for mode in [FindMode::Plain, FindMode::IgnoreCase, FindMode::Regex] {
+                    ui.selectable_value(&mut find.mode, mode, mode.to_string());
                 }
             });
 
-            // Search Bar
-            ui.horizontal(|ui| {
-                ui.label("Search:");
-                let response = ui.add(
-                    egui::TextEdit::singleline(&mut self.search_query)
-                        .hint_text("Filename pattern (e.g. *.txt)"),
-                );
-                ui.checkbox(&mut self.recursive_search, "Recursive");
+        let (enter, shift) = ui.input(|i| (i.key_pressed(egui::Key::Enter), i.modifiers.shift));
+        let submitted = response.lost_focus() && enter;
+        let mut step = None;
+        if ui.button("Prev").clicked() || (submitted && shift) {
+            step = Some(false);
+        }
+        if ui.button("Next").clicked() || (submitted && !shift) {
+            step = Some(true);
+        }
+        if submitted {
+            response.request_focus();
+        }
+        ui.weak("Searches the part of the file loaded around the view");
 
-                if ui.button("Search").clicked()
-                    || (response.lost_focus() && ctx.input(|i| i.key_pressed(egui::Key::Enter)))
-                {
-                    if !self.search_query.is_empty() {
-                        self.is_loading = true;
-                        self.search_files();
+        if let Some(forward) = step
+            && !find.query.is_empty()
+        {
+            match find_bytes_regex(&find.query, find.mode, encoding) {
+                Ok(re) => {
+                    let from = match &paged.found {
+                        Some(found) if forward => found.start + 1,
+                        Some(found) => found.start,
+                        None => paged.top_offset,
+                    };
+                    match paged.find_loaded(&re, from, forward) {
+                        Some(found) => {
+                            paged.jump_to_offset(found.start);
+                            paged.found = Some(found);
+                        }
+                        None => {
+                            notice = Some(
+                                "No more matches in the loaded part; scroll on to load more."
+                                    .to_owned(),
+                            )
+                        }
                     }
                 }
-            });
+                Err(e) => notice = Some(format!("Invalid search: {}", e)),
+            }
+        }
+    });
+    if paged.has_failed_pages() {
+        ui.horizontal(|ui| {
+            ui.colored_label(
+                ui.visuals().warn_fg_color,
+                "Some parts of the file could not be read.",
+            );
+            if ui.button("Retry").clicked() {
+                paged.retry_failed();
+            }
+        });
+    }
+    ui.separator();
 
-            ui.label(&self.status_msg);
-            ui.separator();
+    let rows = paged.visible_rows(visible_rows);
+    let font_id = egui::TextStyle::Monospace.resolve(ui.style());
+    let text_color = ui.visuals().text_color();
+    let gutter_color = ui.visuals().weak_text_color();
+    let slider_width = 16.0;
 
-            // Action Bar
-            ui.horizontal(|ui| {
-                if ui.button("Refresh").clicked() {
-                    self.is_loading = true;
-                    self.list_directory(self.current_path.clone());
-                }
+    ui.horizontal_top(|ui| {
+        let size = egui::vec2(
+            ui.available_width() - slider_width - ui.spacing().item_spacing.x,
+            visible_rows as f32 * row_height,
+        );
+        let (rect, response) = ui.allocate_exact_size(size, egui::Sense::hover());
+        let painter = ui.painter_at(rect);
 
-                if let Some(file) = &self.selected_file {
-                    if ui.button("View").clicked() {
-                        self.is_loading = true;
-                        self.status_msg = format!("Reading {}...", file.name);
-                        self.view_file(file.name.clone());
-                    }
-                    if ui.button("Download").clicked() {
-                        if let Some(path) =
-                            rfd::FileDialog::new().set_file_name(&file.name).save_file()
-                        {
-                            self.is_loading = true;
-                            self.status_msg = format!("Downloading {}...", file.name);
-                            self.download_file(file.name.clone(), path);
-                        }
+        let digits = paged.total_lines.unwrap_or(1).to_string().len().max(4);
+        let char_width = ui.fonts(|f| f.glyph_width(&font_id, '0'));
+        let gutter_width = (digits as f32 + 1.0) * char_width;
+
+        if rows.is_empty() && paged.size > 0 {
+            painter.text(
+                rect.min,
+                egui::Align2::LEFT_TOP,
+                "Loading...",
+                font_id.clone(),
+                gutter_color,
+            );
+        }
+        for (i, row) in rows.iter().enumerate() {
+            let y = rect.top() + i as f32 * row_height;
+            if let Some(line) = row.line_number {
+                painter.text(
+                    egui::pos2(rect.left(), y),
+                    egui::Align2::LEFT_TOP,
+                    format!("{:>width$}", line, width = digits),
+                    font_id.clone(),
+                    gutter_color,
+                );
+            }
+            let pos = egui::pos2(rect.left() + gutter_width, y);
+            let row_end = row.offset + row.bytes.len() as u64;
+            match &paged.found {
+                Some(found) if found.start < row_end && found.end > row.offset => {
+                    let start = (found.start.max(row.offset) - row.offset) as usize;
+                    let end = (found.end.min(row_end) - row.offset) as usize;
+                    let mut job = egui::text::LayoutJob::default();
+                    for (range, background) in [
+                        (0..start, egui::Color32::TRANSPARENT),
+                        (
+                            start..end,
+                            egui::Color32::from_rgba_unmultiplied(255, 140, 0, 160),
+                        ),
+                        (end..row.bytes.len(), egui::Color32::TRANSPARENT),
+                    ] {
+                        let format = egui::TextFormat {
+                            font_id: font_id.clone(),
+                            color: text_color,
+                            background,
+                            ..Default::default()
+                        };
+                        job.append(&decode_row(&row.bytes[range], encoding), 0.0, format);
                     }
+                    let galley = ui.fonts(|f| f.layout_job(job));
+                    painter.galley(pos, galley, text_color);
                 }
+                _ => {
+                    painter.text(
+                        pos,
+                        egui::Align2::LEFT_TOP,
+                        decode_row(&row.bytes, encoding),
+                        font_id.clone(),
+                        text_color,
+                    );
+                }
+            }
+        }
+
+        if response.hovered() {
+            let (scroll, page_up, page_down) = ui.input(|i| {
+                (
+                    i.raw_scroll_delta.y,
+                    i.key_pressed(egui::Key::PageUp),
+                    i.key_pressed(egui::Key::PageDown),
+                )
             });
+            if scroll != 0.0 {
+                let rows = (-scroll / row_height).round() as i64;
+                paged.scroll(if rows == 0 {
+                    -scroll.signum() as i64
+                } else {
+                    rows
+                });
+            }
+            if page_up {
+                paged.scroll(-(visible_rows as i64));
+            }
+            if page_down {
+                paged.scroll(visible_rows as i64);
+            }
+        }
 
-            ui.separator();
+        // The slider range is reversed so that offset 0 is at the top
+        let mut position = paged.top_offset as f64;
+        ui.spacing_mut().slider_width = size.y;
+        let slider = ui.add(
+            egui::Slider::new(&mut position, paged.size as f64..=0.0)
+                .vertical()
+                .show_value(false),
+        );
+        if slider.changed() {
+            paged.jump_to_offset(position as u64);
+        }
+    });
 
-            // File Table
-            let text_height = egui::TextStyle::Body.resolve(ui.style()).size + 5.0; // slightly taller rows
+    if let Some(first) = rows.first() {
+        ui.label(format!("Offset 0x{:X} / 0x{:X}", first.offset, paged.size));
+    }
 
-            TableBuilder::new(ui)
-                .striped(true)
-                .resizable(true)
-                .cell_layout(egui::Layout::left_to_right(egui::Align::Center))
-                .column(Column::auto())
-                .column(Column::auto())
-                .column(Column::auto())
-                .column(Column::remainder())
-                .header(20.0, |mut header| {
-                    header.col(|ui| {
-                        if ui.button("Permissions").clicked() {
-                            self.trigger_sort(SortColumn::Permission);
-                        }
-                    });
-                    header.col(|ui| {
-                        if ui.button("Size").clicked() {
-                            self.trigger_sort(SortColumn::Size);
-                        }
-                    });
-                    header.col(|ui| {
-                        if ui.button("Date").clicked() {
-                            self.trigger_sort(SortColumn::Date);
-                        }
-                    });
-                    header.col(|ui| {
-                        if ui.button("Name").clicked() {
-                            self.trigger_sort(SortColumn::Name);
-                        }
-                    });
-                })
-                .body(|body| {
-                    body.rows(text_height, self.files.len(), |mut row| {
-                        let row_index = row.index();
-                        let file = self.files[row_index].clone();
-                        let is_selected = self
-                            .selected_file
-                            .as_ref()
-                            .map_or(false, |f| f.name == file.name);
+    notice
+}
 
-                        row.col(|ui| {
-                            ui.label(&file.perm);
-                        });
-                        row.col(|ui| {
-                            ui.label(file.size.to_string());
-                        });
-                        row.col(|ui| {
-                            ui.label(&file.date);
-                        });
-                        row.col(|ui| {
-                            let label = ui.selectable_label(is_selected, &file.name);
-                            if label.clicked() {
-                                self.selected_file = Some(file.clone());
-                            }
-                            if label.double_clicked() {
-                                // Navigate if directory?
-                                // ls -l perms start with 'd' for directory
-                                if file.perm.starts_with('d') {
-                                    let new_path = if self.current_path.ends_with('/') {
-                                        format!("{}{}", self.current_path, &file.name)
-                                    } else if self.current_path.is_empty() {
-                                        file.name.clone()
-                                    } else {
-                                        format!("{}/{}", self.current_path, &file.name)
-                                    };
-                                    self.is_loading = true;
-                                    self.list_directory(new_path);
-                                }
-                            }
-                        });
-                    });
-                });
-        });
+/// Decodes one display row, dropping the line terminator and expanding tabs.
+fn decode_row(bytes: &[u8], encoding: FileEncoding) -> String {
+    let (text, _, _) = encoding.coder().decode(bytes);
+    text.trim_end_matches(['\n', '\r']).replace('\t', "    ")
+}
+
+/// rwx checkboxes for user/group/other, special bits and an octal field kept in sync.
+fn permission_editor(ui: &mut egui::Ui, id: &str, mode: &mut u32, octal_input: &mut String) {
+    let mut changed = false;
+
+    egui::Grid::new(id).num_columns(4).show(ui, |ui| {
+        ui.label("");
+        ui.label("Read");
+        ui.label("Write");
+        ui.label("Execute");
+        ui.end_row();
+
+        for (label, shift) in [("User", 6), ("Group", 3), ("Other", 0)] {
+            ui.label(label);
+            for bit in [0o4, 0o2, 0o1] {
+                let mask = bit << shift;
+                let mut enabled = *mode & mask != 0;
+                if ui.checkbox(&mut enabled, "").changed() {
+                    *mode ^= mask;
+                    changed = true;
+                }
+            }
+            ui.end_row();
+        }
+    });
+
+    ui.horizontal(|ui| {
+        for (label, mask) in [("setuid", 0o4000), ("setgid", 0o2000), ("sticky", 0o1000)] {
+            let mut enabled = *mode & mask != 0;
+            if ui.checkbox(&mut enabled, label).changed() {
+                *mode ^= mask;
+                changed = true;
+            }
+        }
+    });
+
+    ui.horizontal(|ui| {
+        ui.label("Octal:");
+        let response = ui.add(egui::TextEdit::singleline(octal_input).desired_width(50.0));
+        if response.changed()
+            && let Some(parsed) = parse_mode_octal(octal_input)
+        {
+            *mode = parsed;
+        }
+        if response.lost_focus() {
+            *octal_input = format_mode_octal(*mode);
+        }
+    });
+
+    if changed {
+        *octal_input = format_mode_octal(*mode);
     }
 }
 
@@ -792,6 +3743,10 @@ impl eframe::App for SshApp {
                         Ok((sess_arc, sftp_arc, path)) => {
                             self.session = Some(sess_arc);
                             self.sftp = Some(sftp_arc);
+                            self.id_names = IdNames::default();
+                            self.nav_history.clear();
+                            self.dir_tree.clear();
+                            self.path_editing = false;
                             self.current_path = path;
                             self.status_msg = "Connected.".to_owned();
                             self.is_connected = true;
@@ -805,17 +3760,41 @@ impl eframe::App for SshApp {
                     }
                 }
                 AppMessage::ListStarted(path) => {
+                    // Navigating away abandons a running search
+                    if let Some(search) = self.name_search.take() {
+                        search.cancel.store(true, Ordering::SeqCst);
+                    }
+                    self.search_base = None;
                     self.is_loading = true;
                     self.files.clear();
                     self.selected_file = None;
+                    self.path_editing = false;
                     self.current_path = path;
+                    // Later navigation only highlights the node; "◎" reveals it
+                    if self.show_tree && self.dir_tree.first_show() {
+                        self.reveal_in_tree();
+                    }
                     self.status_msg = "Listing files...".to_owned();
                 }
-                AppMessage::SearchStarted(query) => {
-                    self.is_loading = true;
-                    self.files.clear();
-                    self.selected_file = None;
-                    self.status_msg = format!("Searching for '{}'...", query);
+                AppMessage::SearchStarted(id) => {
+                    // Results of a replaced or abandoned search are dropped
+                    if let Some(search) = &self.name_search
+                        && search.id == id
+                    {
+                        self.search_base = Some(search.base.clone());
+                        self.is_loading = true;
+                        self.files.clear();
+                        self.selected_file = None;
+                        self.status_msg = format!("Searching for '{}'...", search.query);
+                    }
+                }
+                AppMessage::SearchBatch(id, mut batch) => {
+                    if self.name_search.as_ref().is_some_and(|s| s.id == id) {
+                        self.files.append(&mut batch);
+                        if self.sort_column != SortColumn::None {
+                            self.sort_files();
+                        }
+                    }
                 }
                 AppMessage::ListBatch(mut batch) => {
                     self.files.append(&mut batch);
@@ -823,18 +3802,77 @@ impl eframe::App for SshApp {
                         self.sort_files();
                     }
                 }
+                AppMessage::SearchProgress(id, scanned, found) => {
+                    if let Some(search) = &self.name_search
+                        && search.id == id
+                    {
+                        self.status_msg = format!(
+                            "Searching for '{}'... {} directories scanned, {} found",
+                            search.query, scanned, found
+                        );
+                    }
+                }
+                AppMessage::SearchFinished(id, scanned, stopped, limit) => {
+                    // A search abandoned by navigation was already forgotten,
+                    // and a newer search may be running in its place
+                    if let Some(search) = self.name_search.take_if(|s| s.id == id) {
+                        self.is_loading = false;
+                        self.status_msg = if stopped {
+                            format!(
+                                "Search for '{}' stopped: {} found in {} directories.",
+                                search.query,
+                                self.files.len(),
+                                scanned
+                            )
+                        } else {
+                            format!(
+                                "Found {} matching '{}' in {} directories{}.",
+                                self.files.len(),
+                                search.query,
+                                scanned,
+                                limit.map(|l| format!(" ({})", l)).unwrap_or_default()
+                            )
+                        };
+                        if self.sort_column != SortColumn::None {
+                            self.sort_files();
+                        }
+                    }
+                }
                 AppMessage::ListFinished => {
                     self.is_loading = false;
+                    // Back/Forward and Recent only count directories that listed
+                    self.nav_history.visit(&self.current_path);
                     self.status_msg = format!("Listed {} files.", self.files.len());
                     // Apply sort if active
                     if self.sort_column != SortColumn::None {
                         self.sort_files();
                     }
+                    if let Some(path) = self.pending_reveal.take() {
+                        self.selected_file = self.files.iter().find(|f| f.path == path).cloned();
+                        self.scroll_to_selected = self.selected_file.is_some();
+                    }
+                    // A fresh listing also refreshes the directory's node in the tree
+                    if self.search_base.is_none() {
+                        let dirs = self.files.iter().filter(|f| f.is_dir());
+                        let names = dirs.map(|f| f.name.clone()).collect();
+                        let path = match self.current_path.trim_end_matches('/') {
+                            "" => "/",
+                            path => path,
+                        };
+                        self.dir_tree.set_children(path, Ok(names));
+                    }
                 }
                 AppMessage::ListError(e) => {
                     self.is_loading = false;
+                    self.nav_history.cancel_pending();
                     self.status_msg = format!("List error: {}", e);
                 }
+                AppMessage::SearchError(id, e) => {
+                    if self.name_search.take_if(|s| s.id == id).is_some() {
+                        self.is_loading = false;
+                        self.status_msg = format!("Search error: {}", e);
+                    }
+                }
                 AppMessage::DownloadResult(res) => {
                     self.is_loading = false;
                     match res {
@@ -845,34 +3883,285 @@ impl eframe::App for SshApp {
                 AppMessage::FileContentResult(res) => {
                     self.is_loading = false;
                     match res {
-                        Ok((name, raw_content)) => {
-                            // Default to UTF-8
-                            let decoded_string =
-                                encoding_rs::UTF_8.decode(&raw_content).0.into_owned();
-                            self.viewing_file = Some(FileViewerState {
-                                filename: name,
-                                raw_content,
-                                decoded_content: decoded_string,
-                                encoding: FileEncoding::Utf8,
-                            });
-                            self.status_msg = "File content loaded.".to_owned();
+                        Ok((name, raw_content, stat)) => {
+                            self.open_viewer(ctx, name, raw_content, stat.size, stat.mtime, None);
                         }
                         Err(e) => {
                             self.status_msg = format!("Failed to read file: {}", e);
                         }
                     }
                 }
+                AppMessage::SaveResult(name, res) => {
+                    // The viewer may have moved on to another file during the save
+                    let viewer = self
+                        .viewing_file
+                        .as_mut()
+                        .filter(|state| state.filename == name);
+                    match res {
+                        Ok((content, mtime)) => {
+                            self.status_msg = format!("Saved {}", name);
+                            if let Some(state) = viewer {
+                                state.is_saving = false;
+                                state.raw_content = content;
+                                state.mtime = mtime;
+                                state.modified = false;
+                                if !state.backed_up {
+                                    state.backed_up = true;
+                                    self.status_msg =
+                                        format!("Saved {} (backup: {}.bak)", name, name);
+                                }
+                            }
+                        }
+                        Err(e) => {
+                            if let Some(state) = viewer {
+                                state.is_saving = false;
+                            }
+                            self.status_msg = format!("Failed to save {}: {}", name, e);
+                        }
+                    }
+                }
+                AppMessage::ExternalEditStarted(path) => {
+                    self.status_msg = format!(
+                        "Editing {} externally; saves are uploaded automatically.",
+                        path
+                    );
+                }
+                AppMessage::ExternalEditUploaded(path) => {
+                    if let Some(edit) = self
+                        .external_edits
+                        .iter_mut()
+                        .find(|e| e.remote_path == path)
+                    {
+                        edit.conflict = false;
+                    }
+                    self.status_msg = format!(
+                        "Uploaded {} at {}",
+                        path,
+                        chrono::Local::now().format("%H:%M:%S")
+                    );
+                }
+                AppMessage::ExternalEditConflict(path) => {
+                    if let Some(edit) = self
+                        .external_edits
+                        .iter_mut()
+                        .find(|e| e.remote_path == path)
+                    {
+                        edit.conflict = true;
+                    }
+                    self.status_msg = format!("Not uploaded: {} was modified on the server.", path);
+                }
+                AppMessage::ExternalEditError(path, e) => {
+                    self.status_msg = format!("External edit of {} failed: {}", path, e);
+                }
+                AppMessage::ExternalEditStopped(local_path) => {
+                    self.external_edits.retain(|e| e.local_path != local_path);
+                }
+                AppMessage::FileRangeResult(name, page, res) => {
+                    let paged = match &mut self.viewing_file {
+                        Some(state) if state.filename == name => state.paged.as_mut(),
+                        _ => None,
+                    };
+                    match res {
+                        Ok(data) => {
+                            if let Some(paged) = paged {
+                                paged.insert_page(page, data);
+                            }
+                        }
+                        Err(e) => {
+                            if let Some(paged) = paged {
+                                paged.page_failed(page);
+                            }
+                            self.status_msg = format!("Failed to read {}: {}", name, e);
+                        }
+                    }
+                }
+                AppMessage::DecompressProgress(id, name, written) => {
+                    // Progress from a viewer that was closed may still be queued
+                    if let Some(state) = &mut self.viewing_file
+                        && state.spooling == Some(id)
+                    {
+                        if let Some(paged) = &mut state.paged {
+                            paged.grow(written);
+                        }
+                        self.status_msg =
+                            format!("Decompressing {}... {} MB", name, written / (1024 * 1024));
+                    }
+                }
+                AppMessage::DecompressResult(name, res) => {
+                    self.is_loading = false;
+                    match res {
+                        Ok(decompressed) => {
+                            let Decompressed {
+                                compression,
+                                head,
+                                size,
+                                mtime,
+                                spool,
+                                spooling,
+                            } = decompressed;
+                            self.open_viewer(
+                                ctx,
+                                name,
+                                head,
+                                Some(size),
+                                mtime,
+                                Some((compression, spool, spooling)),
+                            );
+                        }
+                        Err(e) => {
+                            self.status_msg = format!("Failed to decompress {}: {}", name, e);
+                        }
+                    }
+                }
+                AppMessage::DecompressFinished(id, name, res) => {
+                    if let Some(state) = &mut self.viewing_file
+                        && state.spooling == Some(id)
+                    {
+                        state.spooling = None;
+                        match res {
+                            Ok((size, capped)) => {
+                                state.spool_capped = capped;
+                                if let Some(paged) = &mut state.paged {
+                                    paged.grow(size);
+                                    let cancel = paged.cancel.clone();
+                                    let spool = state.spool.clone();
+                                    self.start_line_index(name.clone(), cancel, spool);
+                                }
+                                self.status_msg = if capped {
+                                    format!(
+                                        "{} is larger than {} GB decompressed; showing the first {} GB.",
+                                        name,
+                                        MAX_SPOOL_BYTES >> 30,
+                                        MAX_SPOOL_BYTES >> 30
+                                    )
+                                } else {
+                                    format!("Decompressed {} ({} MB).", name, size >> 20)
+                                };
+                            }
+                            Err(e) => {
+                                self.status_msg = format!("Failed to decompress {}: {}", name, e);
+                            }
+                        }
+                    }
+                }
+                AppMessage::LineIndexProgress(name, update) => {
+                    if let Some(state) = &mut self.viewing_file
+                        && state.filename == name
+                        && let Some(paged) = &mut state.paged
+                    {
+                        paged.apply_index_update(update);
+                    }
+                }
+                AppMessage::FollowResult(id, name, update) => {
+                    // Updates from a stopped follow may still be queued
+                    if let Some(state) = &mut self.viewing_file
+                        && let Some(follow) = &mut state.follow
+                        && follow.id == id
+                    {
+                        if let FollowUpdate::Error(e) = &update {
+                            self.status_msg = format!("Stopped following {}: {}", name, e);
+                        }
+                        follow.apply(update, state.encoding);
+                    }
+                }
+                AppMessage::ContentSearchBatch(id, mut hits, files_scanned) => {
+                    if let Some(search) = &mut self.content_search
+                        && search.id == id
+                    {
+                        search.hits.append(&mut hits);
+                        search.files_scanned = files_scanned;
+                    }
+                }
+                AppMessage::ContentSearchFinished(id, res) => {
+                    if let Some(search) = &mut self.content_search
+                        && search.id == id
+                    {
+                        search.running = false;
+                        match res {
+                            Ok(limit) => {
+                                search.limit = limit;
+                                self.status_msg = format!(
+                                    "Found {} matching lines in {} files.",
+                                    search.hits.len(),
+                                    search.files_scanned
+                                );
+                            }
+                            Err(e) => self.status_msg = format!("Content search failed: {}", e),
+                        }
+                    }
+                }
+                AppMessage::SaveConflict(name) => {
+                    if let Some(state) = &mut self.viewing_file
+                        && state.filename == name
+                    {
+                        state.is_saving = false;
+                        state.conflict = true;
+                    }
+                    self.status_msg = format!("Not saved: {} was modified on the server.", name);
+                }
+                AppMessage::PropertiesResult(res) => match res {
+                    Ok((path, stat)) => self.open_properties_dialog(path, stat),
+                    Err(e) => self.status_msg = format!("Failed to read attributes: {}", e),
+                },
+                AppMessage::TreeChildren(path, result) => {
+                    self.dir_tree.set_children(&path, result);
+                }
+                AppMessage::PathCompletions(dir, names) => {
+                    self.path_completion.set(&dir, names);
+                }
+                AppMessage::IdNamesLoaded(names) => {
+                    self.id_names = names;
+                    if matches!(self.sort_column, SortColumn::Owner | SortColumn::Group) {
+                        self.sort_files();
+                    }
+                }
+                AppMessage::SymlinkResult(res) => {
+                    self.is_loading = false;
+                    match res {
+                        Ok(msg) => {
+                            self.status_msg = msg;
+                            self.refresh_listing();
+                        }
+                        Err(e) => self.status_msg = format!("Failed to create link: {}", e),
+                    }
+                }
+                AppMessage::PermissionsResult(res) => {
+                    self.is_loading = false;
+                    match res {
+                        Ok(msg) => {
+                            self.status_msg = msg;
+                            self.refresh_listing();
+                        }
+                        Err(e) => self.status_msg = format!("Failed to change attributes: {}", e),
+                    }
+                }
             }
         }
 
         if !self.is_connected {
             self.show_login(ctx);
         } else {
+            // Side panels must be added before the central panel
+            if self.show_tree {
+                self.show_tree_panel(ctx);
+            }
+            if self.show_details {
+                self.show_details_panel(ctx);
+            }
             self.show_browser(ctx);
             // Show file viewer modal if active
             if self.viewing_file.is_some() {
                 self.show_file_viewer(ctx);
             }
+            if self.properties_dialog.is_some() {
+                self.show_properties_dialog(ctx);
+            }
+            if self.symlink_dialog.is_some() {
+                self.show_symlink_dialog(ctx);
+            }
+            if self.content_search.is_some() {
+                self.show_content_search(ctx);
+            }
         }
     }
 }
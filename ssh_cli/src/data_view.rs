@@ -0,0 +1,287 @@
+use serde_json::Value;
+use std::cmp::Ordering;
+use std::hash::{DefaultHasher, Hash, Hasher};
+use std::path::Path;
+
+use crate::decompress::Compression;
+
+/// Children listed per JSON object or array before the rest are summarized.
+pub const MAX_TREE_CHILDREN: usize = 1000;
+/// Candidate delimiters, in order of preference when equally consistent.
+const DELIMITERS: &[u8] = b",\t;|";
+/// Lines inspected when guessing the delimiter.
+const SNIFF_LINES: usize = 20;
+
+/// Parsed JSON content. JSON Lines files hold one value per record.
+pub struct JsonDocument {
+    pub values: Vec<Value>,
+    pub lines: bool,
+}
+
+/// Parsed CSV/TSV content. Rows may have differing lengths.
+pub struct Table {
+    pub delimiter: u8,
+    pub rows: Vec<Vec<String>>,
+    pub columns: usize,
+}
+
+/// Tree and table views of the viewer text, re-parsed when the text revision changes.
+#[derive(Default)]
+pub struct DataViewState {
+    pub json: Option<Result<JsonDocument, String>>,
+    pub table: Option<Result<Table, String>>,
+    /// Show the first row as column names rather than data
+    pub has_header: bool,
+    /// (column, ascending)
+    pub sort: Option<(usize, bool)>,
+    /// Data row indices in display order
+    pub order: Vec<usize>,
+    key: u64,
+}
+
+impl DataViewState {
+    /// `revision` must change whenever `text` does.
+    pub fn update(&mut self, remote_path: &str, text: &str, revision: u64) {
+        let mut hasher = DefaultHasher::new();
+        (remote_path, revision).hash(&mut hasher);
+        let key = hasher.finish();
+        if key == self.key {
+            return;
+        }
+        self.key = key;
+
+        // "data.json.gz" is treated like "data.json"
+        let name = match Compression::from_path(remote_path) {
+            Some(_) => remote_path
+                .rsplit_once('.')
+                .map_or(remote_path, |(stem, _)| stem),
+            None => remote_path,
+        };
+        let extension = Path::new(name)
+            .extension()
+            .and_then(|e| e.to_str())
+            .map(|e| e.to_lowercase());
+
+        // Other files get a tree only when they actually hold JSON
+        let named_json = matches!(
+            extension.as_deref(),
+            Some("json" | "jsonl" | "ndjson" | "geojson")
+        );
+        let looks_json = text.trim_start().starts_with(['{', '[']);
+        self.json = if named_json {
+            Some(parse_json(text))
+        } else if looks_json {
+            parse_json(text).ok().map(Ok)
+        } else {
+            None
+        };
+
+        let default_delimiter = match extension.as_deref() {
+            Some("csv") => Some(b','),
+            Some("tsv" | "tab") => Some(b'\t'),
+            Some("psv") => Some(b'|'),
+            _ => None,
+        };
+        self.table = default_delimiter.map(|default| {
+            let delimiter = sniff_delimiter(text).unwrap_or(default);
+            parse_table(text, delimiter)
+        });
+        if let Some(Ok(table)) = &self.table {
+            self.has_header = guess_header(&table.rows);
+        }
+        self.sort = None;
+        self.sort_rows();
+    }
+
+    /// Sorts by a column; clicking the sorted column again reverses it.
+    pub fn sort_by(&mut self, column: usize) {
+        self.sort = match self.sort {
+            Some((c, true)) if c == column => Some((column, false)),
+            _ => Some((column, true)),
+        };
+        self.sort_rows();
+    }
+
+    pub fn set_header(&mut self, has_header: bool) {
+        self.has_header = has_header;
+        self.sort_rows();
+    }
+
+    /// The header row, if the first row is used as one.
+    pub fn header(&self) -> Option<&[String]> {
+        match &self.table {
+            Some(Ok(table)) if self.has_header => table.rows.first().map(Vec::as_slice),
+            _ => None,
+        }
+    }
+
+    fn sort_rows(&mut self) {
+        let Some(Ok(table)) = &self.table else {
+            self.order.clear();
+            return;
+        };
+        let first = usize::from(self.has_header);
+        self.order = (first..table.rows.len().max(first)).collect();
+        if let Some((column, ascending)) = self.sort {
+            let cell = |row: usize| table.rows[row].get(column).map_or("", String::as_str);
+            self.order.sort_by(|&a, &b| {
+                let ordering = compare_cells(cell(a), cell(b));
+                if ascending {
+                    ordering
+                } else {
+                    ordering.reverse()
+                }
+            });
+        }
+    }
+}
+
+/// Parses a JSON document, or a stream of values as in JSON Lines.
+pub fn parse_json(text: &str) -> Result<JsonDocument, String> {
+    let mut values = Vec::new();
+    for value in serde_json::Deserializer::from_str(text).into_iter::<Value>() {
+        values.push(value.map_err(|e| e.to_string())?);
+    }
+    if values.is_empty() {
+        return Err("No JSON value found".to_owned());
+    }
+    Ok(JsonDocument {
+        lines: values.len() > 1,
+        values,
+    })
+}
+
+/// Picks the delimiter that splits the first lines into the same number of
+/// fields, preferring more fields. None if no candidate appears consistently.
+pub fn sniff_delimiter(text: &str) -> Option<u8> {
+    let lines: Vec<&str> = text
+        .lines()
+        .filter(|l| !l.trim().is_empty())
+        .take(SNIFF_LINES)
+        .collect();
+    let mut best: Option<(u8, usize)> = None;
+    for &delimiter in DELIMITERS {
+        let counts: Vec<usize> = lines.iter().map(|l| count_unquoted(l, delimiter)).collect();
+        let Some(&first) = counts.first() else {
+            continue;
+        };
+        if first == 0 || counts.iter().any(|&c| c != first) {
+            continue;
+        }
+        if best.is_none_or(|(_, count)| first > count) {
+            best = Some((delimiter, first));
+        }
+    }
+    best.map(|(delimiter, _)| delimiter)
+}
+
+/// Occurrences of `delimiter` outside double quotes. Quoted fields spanning
+/// lines are rare in the sniffed head and only lower the score.
+fn count_unquoted(line: &str, delimiter: u8) -> usize {
+    let mut quoted = false;
+    let mut count = 0;
+    for &b in line.as_bytes() {
+        if b == b'"' {
+            quoted = !quoted;
+        } else if b == delimiter && !quoted {
+            count += 1;
+        }
+    }
+    count
+}
+
+pub fn parse_table(text: &str, delimiter: u8) -> Result<Table, String> {
+    let mut reader = csv::ReaderBuilder::new()
+        .delimiter(delimiter)
+        .has_headers(false)
+        .flexible(true)
+        .from_reader(text.as_bytes());
+    let mut rows = Vec::new();
+    for record in reader.records() {
+        let record = record.map_err(|e| e.to_string())?;
+        rows.push(record.iter().map(str::to_owned).collect::<Vec<_>>());
+    }
+    let columns = rows.iter().map(Vec::len).max().unwrap_or(0);
+    Ok(Table {
+        delimiter,
+        rows,
+        columns,
+    })
+}
+
+/// A first row of non-empty, non-numeric cells is taken as column names.
+fn guess_header(rows: &[Vec<String>]) -> bool {
+    rows.len() > 1
+        && rows[0]
+            .iter()
+            .all(|cell| !cell.trim().is_empty() && cell.trim().parse::<f64>().is_err())
+}
+
+/// Numbers compare numerically, everything else case-insensitively.
+pub fn compare_cells(a: &str, b: &str) -> Ordering {
+    match (a.trim().parse::<f64>(), b.trim().parse::<f64>()) {
+        (Ok(x), Ok(y)) => x.partial_cmp(&y).unwrap_or(Ordering::Equal),
+        (Ok(_), Err(_)) => Ordering::Less,
+        (Err(_), Ok(_)) => Ordering::Greater,
+        _ => a.to_lowercase().cmp(&b.to_lowercase()),
+    }
+}
+
+/// Name shown in the table toolbar.
+pub fn delimiter_name(delimiter: u8) -> String {
+    match delimiter {
+        b'\t' => "Tab".to_owned(),
+        b',' => "Comma".to_owned(),
+        b';' => "Semicolon".to_owned(),
+        b'|' => "Pipe".to_owned(),
+        other => format!("'{}'", other as char),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_parse_json_and_lines() {
+        let doc = parse_json("{\"b\": 1, \"a\": [true, null]}").unwrap();
+        assert!(!doc.lines);
+        // Keys keep their file order
+        let keys: Vec<_> = doc.values[0].as_object().unwrap().keys().collect();
+        assert_eq!(keys, ["b", "a"]);
+
+        let doc = parse_json("{\"n\": 1}\n{\"n\": 2}\n\n{\"n\": 3}\n").unwrap();
+        assert!(doc.lines);
+        assert_eq!(doc.values.len(), 3);
+
+        assert!(parse_json("{\"n\": 1}\n{oops}\n").is_err());
+        assert!(parse_json("  ").is_err());
+    }
+
+    #[test]
+    fn test_sniff_delimiter() {
+        assert_eq!(sniff_delimiter("a,b,c\n1,2,3\n"), Some(b','));
+        assert_eq!(sniff_delimiter("a\tb\n1\t2,5\n"), Some(b'\t'));
+        assert_eq!(sniff_delimiter("a;b;c\n\"x;y\";2;3\n"), Some(b';'));
+        assert_eq!(sniff_delimiter("just text\nmore text\n"), None);
+    }
+
+    #[test]
+    fn test_table_sorting() {
+        let mut state = DataViewState::default();
+        state.update("/srv/data.csv", "name,size\nb,10\na,9\nC,100\n", 0);
+        assert!(state.has_header);
+        assert_eq!(state.header().map(<[String]>::len), Some(2));
+        assert_eq!(state.order, vec![1, 2, 3]);
+
+        state.sort_by(1);
+        assert_eq!(state.order, vec![2, 1, 3]);
+        state.sort_by(1);
+        assert_eq!(state.order, vec![3, 1, 2]);
+        state.sort_by(0);
+        assert_eq!(state.order, vec![2, 1, 3]);
+
+        state.set_header(false);
+        assert_eq!(state.order.len(), 4);
+    }
+}
@@ -0,0 +1,287 @@
+use ssh2::Sftp;
+use std::fs::File;
+use std::io::{self, BufReader, BufWriter, Read, Seek, SeekFrom, Write};
+use std::path::{Path, PathBuf};
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::{Arc, Mutex, mpsc};
+
+use crate::app::AppMessage;
+use crate::ssh::{open_locked, read_up_to};
+
+/// Output is flushed and reported to the viewer every time this much more has been written.
+const PROGRESS_INTERVAL: u64 = 4 * 1024 * 1024;
+/// Spooling stops here so a decompression bomb cannot fill the local disk.
+pub const MAX_SPOOL_BYTES: u64 = 4 * 1024 * 1024 * 1024;
+
+/// Compressed formats the viewer unpacks on the fly, chosen by extension.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Compression {
+    Gzip,
+    Bzip2,
+    Xz,
+    Zstd,
+}
+
+impl Compression {
+    pub fn from_path(remote_path: &str) -> Option<Compression> {
+        let extension = Path::new(remote_path).extension()?.to_str()?.to_lowercase();
+        match extension.as_str() {
+            "gz" | "tgz" => Some(Compression::Gzip),
+            "bz2" | "tbz2" => Some(Compression::Bzip2),
+            "xz" | "txz" => Some(Compression::Xz),
+            "zst" | "tzst" => Some(Compression::Zstd),
+            _ => None,
+        }
+    }
+
+    /// Wraps `reader` in a decoder. Concatenated streams (as written by
+    /// `cat a.gz b.gz` or parallel compressors) are decoded as one.
+    pub fn decoder<'a>(self, reader: impl Read + 'a) -> io::Result<Box<dyn Read + 'a>> {
+        Ok(match self {
+            Compression::Gzip => Box::new(flate2::read::MultiGzDecoder::new(reader)),
+            Compression::Bzip2 => Box::new(bzip2::read::MultiBzDecoder::new(reader)),
+            Compression::Xz => Box::new(xz2::read::XzDecoder::new_multi_decoder(reader)),
+            Compression::Zstd => Box::new(zstd::stream::read::Decoder::new(reader)?),
+        })
+    }
+}
+
+impl std::fmt::Display for Compression {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            Compression::Gzip => write!(f, "gzip"),
+            Compression::Bzip2 => write!(f, "bzip2"),
+            Compression::Xz => write!(f, "xz"),
+            Compression::Zstd => write!(f, "zstd"),
+        }
+    }
+}
+
+/// Local temp file holding decompressed output too large to keep in memory.
+/// Removed when the last viewer or reader referencing it is dropped.
+#[derive(Debug)]
+pub struct SpoolFile {
+    pub path: PathBuf,
+}
+
+impl Drop for SpoolFile {
+    fn drop(&mut self) {
+        let _ = std::fs::remove_file(&self.path);
+    }
+}
+
+/// Decompressed content for the viewer. `head` holds up to the in-memory
+/// limit; when the output is larger, the rest is still being written to
+/// `spool` and `size` is what has been flushed so far.
+pub struct Decompressed {
+    pub compression: Compression,
+    pub head: Vec<u8>,
+    pub size: u64,
+    pub mtime: Option<u64>,
+    pub spool: Option<Arc<SpoolFile>>,
+    pub spooling: Option<Spooling>,
+}
+
+/// Handle on a decompression that continues after the viewer has opened.
+/// Progress messages carry `id`; the viewer owns `cancel` and sets it when closed.
+pub struct Spooling {
+    pub id: u64,
+    pub cancel: Arc<AtomicBool>,
+}
+
+/// Streams a remote compressed file through its decoder. The session lock is
+/// only held per read, so browsing continues while large files unpack.
+/// Returns as soon as the head is decoded; output past it is left to the
+/// returned `SpoolWriter`, whose messages carry `id`.
+pub fn decompress_remote(
+    sftp_arc: &Arc<Mutex<Sftp>>,
+    remote_path: &str,
+    compression: Compression,
+    limit: usize,
+    id: u64,
+) -> anyhow::Result<(Decompressed, Option<SpoolWriter>)> {
+    let (file, stat) = open_locked(sftp_arc, remote_path)?;
+    let mut decoder = compression.decoder(BufReader::with_capacity(256 * 1024, file))?;
+    let head = read_up_to(&mut decoder, limit as u64)?;
+
+    let mut buffer = vec![0u8; 1024 * 1024];
+    let n = decoder.read(&mut buffer)?;
+    if n == 0 {
+        let decompressed = Decompressed {
+            compression,
+            size: head.len() as u64,
+            head,
+            mtime: stat.mtime,
+            spool: None,
+            spooling: None,
+        };
+        return Ok((decompressed, None));
+    }
+
+    let spool = Arc::new(SpoolFile {
+        path: spool_path(remote_path),
+    });
+    if let Some(dir) = spool.path.parent() {
+        std::fs::create_dir_all(dir)?;
+    }
+    let mut out = BufWriter::new(File::create(&spool.path)?);
+    out.write_all(&head)?;
+    out.write_all(&buffer[..n])?;
+    out.flush()?;
+
+    let spooling = Spooling {
+        id,
+        cancel: Arc::new(AtomicBool::new(false)),
+    };
+    let writer = SpoolWriter {
+        id: spooling.id,
+        cancel: spooling.cancel.clone(),
+        decoder,
+        out,
+        buffer,
+        size: (head.len() + n) as u64,
+        _spool: spool.clone(),
+    };
+    let decompressed = Decompressed {
+        compression,
+        size: writer.size,
+        head,
+        mtime: stat.mtime,
+        spool: Some(spool),
+        spooling: Some(spooling),
+    };
+    Ok((decompressed, Some(writer)))
+}
+
+/// Writes the rest of a decompressed file to the spool in the background.
+pub struct SpoolWriter {
+    id: u64,
+    cancel: Arc<AtomicBool>,
+    decoder: Box<dyn Read>,
+    out: BufWriter<File>,
+    buffer: Vec<u8>,
+    size: u64,
+    // Keeps the file from being removed mid-write if the viewer closes
+    _spool: Arc<SpoolFile>,
+}
+
+impl SpoolWriter {
+    /// Decodes until the end of the input, `MAX_SPOOL_BYTES`, or until the
+    /// viewer is closed. Ends with `DecompressFinished` unless cancelled.
+    pub fn run(mut self, remote_path: &str, tx: &mpsc::Sender<AppMessage>) {
+        let result = self.write_all(remote_path, tx);
+        if self.cancel.load(Ordering::SeqCst) {
+            return;
+        }
+        let _ = tx.send(AppMessage::DecompressFinished(
+            self.id,
+            remote_path.to_string(),
+            result.map_err(|e| e.to_string()),
+        ));
+    }
+
+    /// Returns the final size and whether the output was cut at the cap.
+    fn write_all(
+        &mut self,
+        remote_path: &str,
+        tx: &mpsc::Sender<AppMessage>,
+    ) -> anyhow::Result<(u64, bool)> {
+        let mut reported = self.size;
+        loop {
+            if self.cancel.load(Ordering::SeqCst) {
+                return Ok((self.size, false));
+            }
+            let n = self.decoder.read(&mut self.buffer)?;
+            if n == 0 {
+                break;
+            }
+            let room = (MAX_SPOOL_BYTES - self.size).min(n as u64) as usize;
+            self.out.write_all(&self.buffer[..room])?;
+            self.size += room as u64;
+            if room < n {
+                self.out.flush()?;
+                return Ok((self.size, true));
+            }
+            if self.size - reported >= PROGRESS_INTERVAL {
+                reported = self.size;
+                // Readers only see what has been flushed
+                self.out.flush()?;
+                tx.send(AppMessage::DecompressProgress(
+                    self.id,
+                    remote_path.to_string(),
+                    self.size,
+                ))?;
+            }
+        }
+        self.out.flush()?;
+        Ok((self.size, false))
+    }
+}
+
+fn spool_path(remote_path: &str) -> PathBuf {
+    let name = Path::new(remote_path)
+        .file_stem()
+        .and_then(|n| n.to_str())
+        .unwrap_or("file");
+    std::env::temp_dir().join("ssh_cli_spool").join(format!(
+        "{}-{}-{}",
+        std::process::id(),
+        chrono::Utc::now().timestamp_millis(),
+        name
+    ))
+}
+
+/// Reads up to `len` bytes at `offset` from a spool file.
+pub fn read_spool_range(spool: &SpoolFile, offset: u64, len: u64) -> anyhow::Result<Vec<u8>> {
+    let mut file = File::open(&spool.path)?;
+    file.seek(SeekFrom::Start(offset))?;
+    read_up_to(&mut file, len)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_compression_from_path() {
+        assert_eq!(
+            Compression::from_path("/var/log/app.log.3.gz"),
+            Some(Compression::Gzip)
+        );
+        assert_eq!(
+            Compression::from_path("dump.sql.BZ2"),
+            Some(Compression::Bzip2)
+        );
+        assert_eq!(Compression::from_path("a.xz"), Some(Compression::Xz));
+        assert_eq!(Compression::from_path("a.tar.zst"), Some(Compression::Zstd));
+        assert_eq!(Compression::from_path("/var/log/app.log"), None);
+    }
+
+    #[test]
+    fn test_decoders_round_trip() {
+        let text = b"line 1\nline 2\n".repeat(100);
+
+        let mut gz = flate2::write::GzEncoder::new(Vec::new(), flate2::Compression::default());
+        gz.write_all(&text).unwrap();
+        let gz = gz.finish().unwrap();
+        // Two concatenated members decode as one stream
+        let doubled = [gz.clone(), gz].concat();
+
+        let mut out = Vec::new();
+        Compression::Gzip
+            .decoder(doubled.as_slice())
+            .unwrap()
+            .read_to_end(&mut out)
+            .unwrap();
+        assert_eq!(out, [text.clone(), text.clone()].concat());
+
+        let zst = zstd::encode_all(text.as_slice(), 0).unwrap();
+        let mut out = Vec::new();
+        Compression::Zstd
+            .decoder(zst.as_slice())
+            .unwrap()
+            .read_to_end(&mut out)
+            .unwrap();
+        assert_eq!(out, text);
+    }
+}
@@ -0,0 +1,215 @@
+use std::collections::{HashMap, HashSet};
+
+/// Subdirectories of a tree node as far as they are known.
+#[derive(Debug, Clone, PartialEq)]
+pub enum Children {
+    Loading,
+    Loaded(Vec<String>),
+    Failed(String),
+}
+
+/// One line of the tree panel.
+#[derive(Debug, Clone, PartialEq)]
+pub enum TreeRow {
+    Dir {
+        path: String,
+        name: String,
+        depth: usize,
+        expanded: bool,
+        /// Known to have no subdirectories
+        leaf: bool,
+    },
+    /// "Loading..." or an error under an expanded directory
+    Note { depth: usize, text: String },
+}
+
+/// Directory tree rooted at "/". Children are fetched the first time a
+/// node is expanded and kept, together with the expansion state, until the
+/// session ends.
+#[derive(Debug, Default)]
+pub struct DirTree {
+    children: HashMap<String, Children>,
+    expanded: HashSet<String>,
+    /// Set once the panel has been shown in this session
+    shown: bool,
+}
+
+impl DirTree {
+    /// True the first time the panel is shown after connecting, when the
+    /// current directory is revealed; later it is only highlighted.
+    pub fn first_show(&mut self) -> bool {
+        !std::mem::replace(&mut self.shown, true)
+    }
+
+    /// Expands or collapses a node; true if its children have to be fetched.
+    pub fn toggle(&mut self, path: &str) -> bool {
+        if self.expanded.remove(path) {
+            return false;
+        }
+        self.expand(path)
+    }
+
+    fn expand(&mut self, path: &str) -> bool {
+        self.expanded.insert(path.to_string());
+        if self.children.contains_key(path) {
+            return false;
+        }
+        self.children.insert(path.to_string(), Children::Loading);
+        true
+    }
+
+    /// Expands every ancestor of `path` so that it shows in the tree, and
+    /// returns the ones whose children have to be fetched.
+    pub fn reveal(&mut self, path: &str) -> Vec<String> {
+        let mut to_load = Vec::new();
+        let mut ancestor = String::from("/");
+        let mut segments = path.split('/').filter(|s| !s.is_empty()).peekable();
+        while let Some(segment) = segments.next() {
+            if self.expand(&ancestor) {
+                to_load.push(ancestor.clone());
+            }
+            if segments.peek().is_some() {
+                ancestor = join(&ancestor, segment);
+            }
+        }
+        to_load
+    }
+
+    /// Stores fetched children; also used with a fresh listing of the directory.
+    pub fn set_children(&mut self, path: &str, result: Result<Vec<String>, String>) {
+        let children = match result {
+            Ok(mut names) => {
+                names.sort();
+                Children::Loaded(names)
+            }
+            Err(e) => Children::Failed(e),
+        };
+        self.children.insert(path.to_string(), children);
+    }
+
+    /// Forgets all fetched children; returns the expanded nodes to fetch again.
+    pub fn reload(&mut self) -> Vec<String> {
+        self.children.clear();
+        let mut paths: Vec<String> = self.expanded.iter().cloned().collect();
+        paths.sort();
+        for path in &paths {
+            self.children.insert(path.clone(), Children::Loading);
+        }
+        paths
+    }
+
+    pub fn clear(&mut self) {
+        *self = Self::default();
+    }
+
+    /// Visible rows in display order.
+    pub fn rows(&self) -> Vec<TreeRow> {
+        let mut rows = Vec::new();
+        self.push_rows("/", "/", 0, &mut rows);
+        rows
+    }
+
+    fn push_rows(&self, path: &str, name: &str, depth: usize, rows: &mut Vec<TreeRow>) {
+        let expanded = self.expanded.contains(path);
+        let children = self.children.get(path);
+        rows.push(TreeRow::Dir {
+            path: path.to_string(),
+            name: name.to_string(),
+            depth,
+            expanded,
+            leaf: matches!(children, Some(Children::Loaded(names)) if names.is_empty()),
+        });
+        if !expanded {
+            return;
+        }
+        let note = |text: String| TreeRow::Note {
+            depth: depth + 1,
+            text,
+        };
+        match children {
+            Some(Children::Loaded(names)) => {
+                for child in names {
+                    self.push_rows(&join(path, child), child, depth + 1, rows);
+                }
+            }
+            Some(Children::Failed(e)) => rows.push(note(e.clone())),
+            Some(Children::Loading) | None => rows.push(note("Loading...".to_string())),
+        }
+    }
+}
+
+fn join(dir: &str, name: &str) -> String {
+    if dir.ends_with('/') {
+        format!("{}{}", dir, name)
+    } else {
+        format!("{}/{}", dir, name)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn dir_paths(tree: &DirTree) -> Vec<String> {
+        tree.rows()
+            .into_iter()
+            .filter_map(|row| match row {
+                TreeRow::Dir { path, .. } => Some(path),
+                TreeRow::Note { .. } => None,
+            })
+            .collect()
+    }
+
+    #[test]
+    fn test_reveal_loads_ancestors_once() {
+        let mut tree = DirTree::default();
+        assert_eq!(tree.reveal("/home/user/src"), ["/", "/home", "/home/user"]);
+        assert_eq!(
+            tree.rows()[1],
+            TreeRow::Note {
+                depth: 1,
+                text: "Loading...".to_string()
+            }
+        );
+
+        tree.set_children("/", Ok(vec!["home".into(), "etc".into()]));
+        tree.set_children("/home", Ok(vec!["user".into()]));
+        tree.set_children("/home/user", Ok(vec!["src".into()]));
+        assert_eq!(
+            dir_paths(&tree),
+            ["/", "/etc", "/home", "/home/user", "/home/user/src"]
+        );
+        // Already fetched: nothing to load again
+        assert!(tree.reveal("/home/user").is_empty());
+    }
+
+    #[test]
+    fn test_first_show_once_per_session() {
+        let mut tree = DirTree::default();
+        assert!(tree.first_show());
+        assert!(!tree.first_show());
+        tree.clear();
+        assert!(tree.first_show());
+    }
+
+    #[test]
+    fn test_expansion_state_is_kept() {
+        let mut tree = DirTree::default();
+        tree.reveal("/var/log");
+        tree.set_children("/", Ok(vec!["var".into()]));
+        tree.set_children("/var", Ok(vec!["log".into(), "empty".into()]));
+        tree.set_children("/var/empty", Ok(Vec::new()));
+
+        // Collapsing keeps the children, so expanding needs no fetch
+        assert!(!tree.toggle("/var"));
+        assert_eq!(dir_paths(&tree), ["/", "/var"]);
+        assert!(!tree.toggle("/var"));
+        assert!(tree.toggle("/var/log"));
+
+        let leaf = tree.rows().into_iter().any(
+            |row| matches!(row, TreeRow::Dir { path, leaf: true, .. } if path == "/var/empty"),
+        );
+        assert!(leaf);
+        assert_eq!(tree.reload(), ["/", "/var", "/var/log"]);
+    }
+}
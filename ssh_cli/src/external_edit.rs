@@ -0,0 +1,237 @@
+use ssh2::Sftp;
+use std::path::{Path, PathBuf};
+use std::process::Command;
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::{Arc, Mutex, mpsc};
+use std::thread;
+use std::time::{Duration, SystemTime};
+
+use crate::app::AppMessage;
+use crate::ssh::{RemoteModifiedError, download_to_local, write_file_content};
+
+const POLL_INTERVAL: Duration = Duration::from_secs(1);
+
+/// A remote file opened in an external editor.
+/// The local copy is polled and uploaded back on every save until stopped.
+pub struct ExternalEdit {
+    pub remote_path: String,
+    pub local_path: PathBuf,
+    pub conflict: bool,
+    stop: Arc<AtomicBool>,
+    force_upload: Arc<AtomicBool>,
+}
+
+impl ExternalEdit {
+    /// Downloads `remote_path` to a temp dir, launches `editor` on it and starts watching.
+    pub fn start(
+        sftp_arc: Arc<Mutex<Sftp>>,
+        remote_path: String,
+        editor: String,
+        tx: mpsc::Sender<AppMessage>,
+    ) -> Self {
+        let file_name = Path::new(&remote_path)
+            .file_name()
+            .and_then(|n| n.to_str())
+            .unwrap_or("file")
+            .to_string();
+        // One directory per edit so the editor sees the original file name
+        let local_path = std::env::temp_dir()
+            .join("ssh_cli_edit")
+            .join(format!(
+                "{}-{}",
+                std::process::id(),
+                chrono::Utc::now().timestamp_millis()
+            ))
+            .join(file_name);
+
+        let stop = Arc::new(AtomicBool::new(false));
+        let force_upload = Arc::new(AtomicBool::new(false));
+
+        let watcher = Watcher {
+            sftp_arc,
+            remote_path: remote_path.clone(),
+            local_path: local_path.clone(),
+            stop: stop.clone(),
+            force_upload: force_upload.clone(),
+            tx: tx.clone(),
+        };
+        thread::spawn(move || {
+            if let Err(e) = watcher.run(&editor) {
+                let _ = tx.send(AppMessage::ExternalEditError(
+                    watcher.remote_path.clone(),
+                    e.to_string(),
+                ));
+            }
+            // The local copy holds remote contents; don't leave it behind
+            if let Some(dir) = watcher.local_path.parent() {
+                let _ = std::fs::remove_dir_all(dir);
+            }
+            let _ = tx.send(AppMessage::ExternalEditStopped(watcher.local_path));
+        });
+
+        Self {
+            remote_path,
+            local_path,
+            conflict: false,
+            stop,
+            force_upload,
+        }
+    }
+
+    pub fn stop(&self) {
+        self.stop.store(true, Ordering::SeqCst);
+    }
+
+    /// Uploads the local copy on the next poll even though the remote file changed.
+    pub fn upload_anyway(&mut self) {
+        self.conflict = false;
+        self.force_upload.store(true, Ordering::SeqCst);
+    }
+}
+
+struct Watcher {
+    sftp_arc: Arc<Mutex<Sftp>>,
+    remote_path: String,
+    local_path: PathBuf,
+    stop: Arc<AtomicBool>,
+    force_upload: Arc<AtomicBool>,
+    tx: mpsc::Sender<AppMessage>,
+}
+
+impl Watcher {
+    fn run(&self, editor: &str) -> anyhow::Result<()> {
+        if let Some(dir) = self.local_path.parent() {
+            std::fs::create_dir_all(dir)?;
+        }
+        let mut remote_mtime =
+            download_to_local(&self.sftp_arc, &self.remote_path, &self.local_path)?;
+        launch_editor(editor, &self.local_path)?;
+        let _ = self
+            .tx
+            .send(AppMessage::ExternalEditStarted(self.remote_path.clone()));
+
+        let mut last_modified = local_modified(&self.local_path)?;
+        loop {
+            // A save made just before Stop still gets one last poll
+            let stopping = self.stop.load(Ordering::SeqCst);
+            if !stopping {
+                thread::sleep(POLL_INTERVAL);
+            }
+            self.poll(&mut last_modified, &mut remote_mtime);
+            if stopping {
+                break;
+            }
+        }
+        Ok(())
+    }
+
+    /// Uploads the local copy if it changed since the last poll or an upload was forced.
+    fn poll(&self, last_modified: &mut SystemTime, remote_mtime: &mut Option<u64>) {
+        // Editors that save via rename can briefly leave no file behind
+        let Ok(modified) = local_modified(&self.local_path) else {
+            return;
+        };
+        let forced = self.force_upload.swap(false, Ordering::SeqCst);
+        if modified == *last_modified && !forced {
+            return;
+        }
+        *last_modified = modified;
+
+        let Ok(content) = std::fs::read(&self.local_path) else {
+            return;
+        };
+        let expected_mtime = if forced { None } else { *remote_mtime };
+        match write_file_content(
+            &self.sftp_arc,
+            &self.remote_path,
+            &content,
+            None,
+            expected_mtime,
+        ) {
+            Ok(mtime) => {
+                *remote_mtime = mtime;
+                let _ = self
+                    .tx
+                    .send(AppMessage::ExternalEditUploaded(self.remote_path.clone()));
+            }
+            Err(e) if e.is::<RemoteModifiedError>() => {
+                let _ = self
+                    .tx
+                    .send(AppMessage::ExternalEditConflict(self.remote_path.clone()));
+            }
+            Err(e) => {
+                let _ = self.tx.send(AppMessage::ExternalEditError(
+                    self.remote_path.clone(),
+                    e.to_string(),
+                ));
+            }
+        }
+    }
+}
+
+/// Runs the configured editor command (program plus optional arguments) on `file`.
+fn launch_editor(command: &str, file: &Path) -> anyhow::Result<()> {
+    let mut parts = split_command(command).into_iter();
+    let program = parts
+        .next()
+        .ok_or_else(|| anyhow::anyhow!("No external editor configured"))?;
+    Command::new(program).args(parts).arg(file).spawn()?;
+    Ok(())
+}
+
+/// Splits a command line on whitespace, keeping single- or double-quoted parts
+/// together so paths like `"C:\Program Files\..."` work. Backslashes are literal.
+fn split_command(command: &str) -> Vec<String> {
+    let mut parts = Vec::new();
+    let mut current = String::new();
+    let mut in_part = false;
+    let mut quote = None;
+
+    for c in command.chars() {
+        match quote {
+            Some(q) if c == q => quote = None,
+            Some(_) => current.push(c),
+            None if c == '"' || c == '\'' => {
+                quote = Some(c);
+                in_part = true;
+            }
+            None if c.is_whitespace() => {
+                if in_part {
+                    parts.push(std::mem::take(&mut current));
+                    in_part = false;
+                }
+            }
+            None => {
+                current.push(c);
+                in_part = true;
+            }
+        }
+    }
+    if in_part {
+        parts.push(current);
+    }
+    parts
+}
+
+fn local_modified(path: &Path) -> anyhow::Result<SystemTime> {
+    Ok(std::fs::metadata(path)?.modified()?)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_split_command() {
+        assert_eq!(split_command("code --wait"), vec!["code", "--wait"]);
+        assert_eq!(
+            split_command(r#""C:\Program Files\Notepad++\notepad++.exe" -multiInst"#),
+            vec![r"C:\Program Files\Notepad++\notepad++.exe", "-multiInst"]
+        );
+        assert_eq!(
+            split_command("'/opt/my editor/bin/ed'  -n ''"),
+            vec!["/opt/my editor/bin/ed", "-n", ""]
+        );
+        assert!(split_command("   ").is_empty());
+    }
+}
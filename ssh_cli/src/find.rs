@@ -0,0 +1,311 @@
+use egui::Color32;
+use egui::text::LayoutJob;
+use serde::{Deserialize, Serialize};
+use std::hash::{DefaultHasher, Hash, Hasher};
+use std::ops::Range;
+
+use crate::model::FileEncoding;
+
+/// How the query of the viewer's find bar is interpreted.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, Serialize, Deserialize)]
+pub enum FindMode {
+    Plain,
+    IgnoreCase,
+    Regex,
+}
+
+impl std::fmt::Display for FindMode {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            FindMode::Plain => write!(f, "Match case"),
+            FindMode::IgnoreCase => write!(f, "Ignore case"),
+            FindMode::Regex => write!(f, "Regex"),
+        }
+    }
+}
+
+/// Find bar state. Matches are byte ranges into the decoded text and are
+/// recomputed whenever the text revision, query or mode changes.
+pub struct FindState {
+    pub query: String,
+    pub mode: FindMode,
+    pub matches: Vec<Range<usize>>,
+    pub current: Option<usize>,
+    /// Invalid regex message
+    pub error: Option<String>,
+    key: u64,
+}
+
+impl Default for FindState {
+    fn default() -> Self {
+        Self {
+            query: String::new(),
+            mode: FindMode::IgnoreCase,
+            matches: Vec::new(),
+            current: None,
+            error: None,
+            key: 0,
+        }
+    }
+}
+
+impl FindState {
+    /// `revision` must change whenever `text` does.
+    pub fn update(&mut self, text: &str, revision: u64) {
+        let mut hasher = DefaultHasher::new();
+        (revision, &self.query, self.mode).hash(&mut hasher);
+        let key = hasher.finish();
+        if key == self.key {
+            return;
+        }
+        self.key = key;
+
+        match find_matches(text, &self.query, self.mode) {
+            Ok(matches) => {
+                self.matches = matches;
+                self.error = None;
+            }
+            Err(e) => {
+                self.matches.clear();
+                self.error = Some(e);
+            }
+        }
+        self.current = match self.current {
+            _ if self.matches.is_empty() => None,
+            Some(i) => Some(i.min(self.matches.len() - 1)),
+            None => None,
+        };
+    }
+
+    /// Moves to the next (or previous) match, wrapping around. Returns its range.
+    pub fn step(&mut self, forward: bool) -> Option<Range<usize>> {
+        let count = self.matches.len();
+        if count == 0 {
+            return None;
+        }
+        let next = match (self.current, forward) {
+            (None, true) => 0,
+            (None, false) => count - 1,
+            (Some(i), true) => (i + 1) % count,
+            (Some(i), false) => (i + count - 1) % count,
+        };
+        self.current = Some(next);
+        Some(self.matches[next].clone())
+    }
+}
+
+pub fn find_matches(text: &str, query: &str, mode: FindMode) -> Result<Vec<Range<usize>>, String> {
+    if query.is_empty() {
+        return Ok(Vec::new());
+    }
+    if mode == FindMode::Plain {
+        return Ok(text
+            .match_indices(query)
+            .map(|(i, m)| i..i + m.len())
+            .collect());
+    }
+    let re = find_regex(query, mode)?;
+    // Empty matches (e.g. "^") cannot be highlighted or navigated to
+    Ok(re
+        .find_iter(text)
+        .map(|m| m.range())
+        .filter(|r| !r.is_empty())
+        .collect())
+}
+
+/// Compiles the query as a regex; plain queries are escaped.
+pub fn find_regex(query: &str, mode: FindMode) -> Result<regex::Regex, String> {
+    let pattern = match mode {
+        FindMode::Plain => regex::escape(query),
+        FindMode::IgnoreCase => format!("(?i){}", regex::escape(query)),
+        FindMode::Regex => query.to_string(),
+    };
+    regex::Regex::new(&pattern).map_err(|e| e.to_string())
+}
+
+/// Compiles the query for searching raw file bytes (the paged viewer).
+/// Plain and ignore-case queries are matched as `encoding` bytes; regexes
+/// only match text that is also valid UTF-8 (ASCII in most encodings).
+pub fn find_bytes_regex(
+    query: &str,
+    mode: FindMode,
+    encoding: FileEncoding,
+) -> Result<regex::bytes::Regex, String> {
+    let pattern = match mode {
+        FindMode::Regex => query.to_string(),
+        _ if encoding == FileEncoding::Utf8 => find_regex(query, mode)?.as_str().to_string(),
+        _ => {
+            let (bytes, had_errors) = encoding.encode(query);
+            if had_errors {
+                return Err(format!("Not representable in {}", encoding));
+            }
+            let escaped: String = bytes.iter().map(|b| format!("\\x{:02X}", b)).collect();
+            let flags = if mode == FindMode::IgnoreCase {
+                "i-u"
+            } else {
+                "-u"
+            };
+            format!("(?{}){}", flags, escaped)
+        }
+    };
+    regex::bytes::Regex::new(&pattern).map_err(|e| e.to_string())
+}
+
+/// Byte offset of the start of a 1-based line.
+pub fn line_start(text: &str, line: usize) -> Option<usize> {
+    if line == 0 {
+        return None;
+    }
+    if line == 1 {
+        return Some(0);
+    }
+    text.match_indices('\n').nth(line - 2).map(|(i, _)| i + 1)
+}
+
+/// Paints match backgrounds into a laid-out job, splitting sections at match
+/// boundaries. Ranges outside the job's text are ignored.
+pub fn highlight_matches(
+    job: &mut LayoutJob,
+    matches: &[Range<usize>],
+    current: Option<usize>,
+    color: Color32,
+    current_color: Color32,
+) {
+    // Matches computed on an older version of the text may run past its end
+    let len = job.text.len();
+    let matches = &matches[..matches.partition_point(|m| m.end <= len)];
+    if matches.is_empty() {
+        return;
+    }
+    let sections = std::mem::take(&mut job.sections);
+    let mut next_match = 0;
+    for section in sections {
+        let mut pos = section.byte_range.start;
+        let end = section.byte_range.end;
+        while pos < end {
+            // Skip matches that end before this position
+            while next_match < matches.len() && matches[next_match].end <= pos {
+                next_match += 1;
+            }
+            let (piece_end, background) = match matches.get(next_match) {
+                Some(m) if m.start <= pos => {
+                    let bg = if current == Some(next_match) {
+                        current_color
+                    } else {
+                        color
+                    };
+                    (m.end.min(end), Some(bg))
+                }
+                Some(m) if m.start < end => (m.start, None),
+                _ => (end, None),
+            };
+            let mut piece = section.clone();
+            piece.byte_range = pos..piece_end;
+            if let Some(bg) = background {
+                piece.format.background = bg;
+            }
+            job.sections.push(piece);
+            pos = piece_end;
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use egui::FontId;
+
+    #[test]
+    fn test_find_matches_modes() {
+        let text = "Error: foo\nerror: bar\nERROR";
+        let plain = find_matches(text, "error", FindMode::Plain).unwrap();
+        assert_eq!(plain.len(), 1);
+        assert_eq!(plain[0], 11..16);
+        assert_eq!(
+            find_matches(text, "error", FindMode::IgnoreCase).map(|m| m.len()),
+            Ok(3)
+        );
+        assert_eq!(
+            find_matches(text, r"^\w+:", FindMode::Regex).map(|m| m.len()),
+            Ok(1)
+        );
+        assert_eq!(
+            find_matches(text, r"(?m)^\w+:", FindMode::Regex).map(|m| m.len()),
+            Ok(2)
+        );
+        assert!(find_matches(text, "(", FindMode::Regex).is_err());
+        assert_eq!(find_matches(text, "a.b", FindMode::IgnoreCase), Ok(vec![]));
+    }
+
+    #[test]
+    fn test_step_wraps() {
+        let mut find = FindState {
+            query: "a".to_string(),
+            ..Default::default()
+        };
+        find.update("a-a-a", 0);
+        assert_eq!(find.step(true), Some(0..1));
+        assert_eq!(find.step(false), Some(4..5));
+        assert_eq!(find.step(true), Some(0..1));
+    }
+
+    #[test]
+    fn test_update_follows_revision() {
+        let mut find = FindState {
+            query: "a".to_string(),
+            ..Default::default()
+        };
+        find.update("a-a", 0);
+        assert_eq!(find.matches.len(), 2);
+        // Same revision: the text is not scanned again
+        find.update("a-a-a", 0);
+        assert_eq!(find.matches.len(), 2);
+        find.update("a-a-a", 1);
+        assert_eq!(find.matches.len(), 3);
+    }
+
+    #[test]
+    fn test_find_bytes_regex() {
+        let re = find_bytes_regex("error", FindMode::IgnoreCase, FileEncoding::Utf8).unwrap();
+        assert_eq!(re.find(b"an ERROR here").map(|m| m.range()), Some(3..8));
+
+        let (sjis, _, _) = encoding_rs::SHIFT_JIS.encode("ログ: エラー発生");
+        let re = find_bytes_regex("エラー", FindMode::Plain, FileEncoding::ShiftJis).unwrap();
+        assert_eq!(re.find(&sjis).map(|m| m.start()), Some(6));
+        let re = find_bytes_regex("Ab", FindMode::IgnoreCase, FileEncoding::ShiftJis).unwrap();
+        assert!(re.is_match(b"xaBx"));
+
+        assert!(find_bytes_regex("(", FindMode::Regex, FileEncoding::Utf8).is_err());
+    }
+
+    #[test]
+    fn test_line_start() {
+        let text = "one\ntwo\nthree";
+        assert_eq!(line_start(text, 1), Some(0));
+        assert_eq!(line_start(text, 3), Some(8));
+        assert_eq!(line_start(text, 4), None);
+        assert_eq!(line_start(text, 0), None);
+    }
+
+    #[test]
+    fn test_highlight_matches_splits_sections() {
+        let mut job = LayoutJob::simple(
+            "abcdef".to_string(),
+            FontId::monospace(12.0),
+            Color32::WHITE,
+            f32::INFINITY,
+        );
+        highlight_matches(
+            &mut job,
+            &[1..3, 4..5],
+            Some(1),
+            Color32::RED,
+            Color32::GREEN,
+        );
+        let ranges: Vec<_> = job.sections.iter().map(|s| s.byte_range.clone()).collect();
+        assert_eq!(ranges, vec![0..1, 1..3, 3..4, 4..5, 5..6]);
+        assert_eq!(job.sections[1].format.background, Color32::RED);
+        assert_eq!(job.sections[3].format.background, Color32::GREEN);
+        assert_eq!(job.sections[2].format.background, Color32::TRANSPARENT);
+    }
+}
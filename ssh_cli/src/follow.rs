@@ -0,0 +1,217 @@
+use ssh2::{File, Sftp};
+use std::io::{Seek, SeekFrom};
+use std::path::Path;
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::{Arc, Mutex, mpsc};
+use std::thread;
+use std::time::Duration;
+
+use crate::app::AppMessage;
+use crate::model::{FileEncoding, FollowUpdate};
+use crate::ssh::read_up_to;
+
+const POLL_INTERVAL: Duration = Duration::from_secs(1);
+/// Existing content shown when following starts.
+const INITIAL_TAIL_BYTES: u64 = 64 * 1024;
+/// Upper bound on one read so a burst of output does not hold the SFTP lock for long.
+const MAX_READ_BYTES: u64 = 1024 * 1024;
+/// The view keeps at most this many bytes; older lines are dropped.
+const MAX_BUFFER_BYTES: usize = 2 * 1024 * 1024;
+
+/// Live `tail -f` view of a remote file. Polling stops when this is dropped.
+pub struct FileFollow {
+    /// Tells this follow's updates apart from those of an earlier one on the same file
+    pub id: u64,
+    buffer: Vec<u8>,
+    /// `buffer` decoded with the viewer's encoding
+    pub text: String,
+    /// Bytes discarded from the front to keep the buffer bounded
+    pub dropped_bytes: u64,
+    /// Set while the user has scrolled away from the bottom
+    pub paused: bool,
+    /// Bytes that arrived while paused
+    pub unseen_bytes: u64,
+    pub stopped: bool,
+    stop: Arc<AtomicBool>,
+}
+
+impl Drop for FileFollow {
+    fn drop(&mut self) {
+        self.stop.store(true, Ordering::SeqCst);
+    }
+}
+
+impl FileFollow {
+    /// Starts polling `remote_path`, beginning with the last few KB of the file.
+    pub fn start(
+        sftp_arc: Arc<Mutex<Sftp>>,
+        id: u64,
+        remote_path: String,
+        tx: mpsc::Sender<AppMessage>,
+    ) -> Self {
+        let stop = Arc::new(AtomicBool::new(false));
+        let worker_stop = stop.clone();
+        thread::spawn(move || {
+            if let Err(e) = follow_worker(&sftp_arc, &remote_path, id, &worker_stop, &tx) {
+                let _ = tx.send(AppMessage::FollowResult(
+                    id,
+                    remote_path,
+                    FollowUpdate::Error(e.to_string()),
+                ));
+            }
+        });
+
+        Self {
+            id,
+            buffer: Vec::new(),
+            text: String::new(),
+            dropped_bytes: 0,
+            paused: false,
+            unseen_bytes: 0,
+            stopped: false,
+            stop,
+        }
+    }
+
+    pub fn apply(&mut self, update: FollowUpdate, encoding: FileEncoding) {
+        match update {
+            FollowUpdate::Data(data) => {
+                if self.paused {
+                    self.unseen_bytes += data.len() as u64;
+                }
+                self.buffer.extend_from_slice(&data);
+            }
+            FollowUpdate::Truncated => self.buffer.extend_from_slice(b"\n--- file truncated ---\n"),
+            FollowUpdate::Rotated => self.buffer.extend_from_slice(b"\n--- file rotated ---\n"),
+            FollowUpdate::Error(_) => {
+                self.stopped = true;
+                return;
+            }
+        }
+        self.dropped_bytes += trim_front(&mut self.buffer, MAX_BUFFER_BYTES) as u64;
+        self.decode(encoding);
+    }
+
+    pub fn decode(&mut self, encoding: FileEncoding) {
+        let (text, _, _) = encoding.coder().decode(&self.buffer);
+        self.text = text.into_owned();
+    }
+}
+
+/// Drops bytes from the front so at most `cap` remain, cutting at a line
+/// boundary when one is available. Returns the number of bytes removed.
+fn trim_front(buffer: &mut Vec<u8>, cap: usize) -> usize {
+    if buffer.len() <= cap {
+        return 0;
+    }
+    let excess = buffer.len() - cap;
+    let cut = buffer[excess..]
+        .iter()
+        .position(|&b| b == b'\n')
+        .map_or(excess, |i| excess + i + 1);
+    buffer.drain(..cut);
+    cut
+}
+
+/// Reads appended bytes until stopped. A shrinking file is treated as
+/// truncated; a path whose size or mtime no longer matches the open handle
+/// on two consecutive polls is treated as rotated and reopened.
+fn follow_worker(
+    sftp_arc: &Arc<Mutex<Sftp>>,
+    remote_path: &str,
+    id: u64,
+    stop: &AtomicBool,
+    tx: &mpsc::Sender<AppMessage>,
+) -> anyhow::Result<()> {
+    let (mut file, mut offset) = {
+        let sftp = sftp_arc.lock().map_err(|_| anyhow::anyhow!("Lock error"))?;
+        let mut file = sftp.open(Path::new(remote_path))?;
+        let size = file.stat()?.size.unwrap_or(0);
+        (file, size.saturating_sub(INITIAL_TAIL_BYTES))
+    };
+    let mut rotation_suspected = false;
+
+    while !stop.load(Ordering::SeqCst) {
+        let mut updates = Vec::new();
+        let mut caught_up = true;
+        {
+            let sftp = sftp_arc.lock().map_err(|_| anyhow::anyhow!("Lock error"))?;
+            let stat = file.stat()?;
+            let size = stat.size.unwrap_or(0);
+            if size < offset {
+                offset = 0;
+                updates.push(FollowUpdate::Truncated);
+            }
+
+            if size > offset {
+                let data = read_at(&mut file, offset, MAX_READ_BYTES)?;
+                offset += data.len() as u64;
+                caught_up = (data.len() as u64) < MAX_READ_BYTES;
+                if !data.is_empty() {
+                    updates.push(FollowUpdate::Data(data));
+                }
+                rotation_suspected = false;
+            } else {
+                match sftp.stat(Path::new(remote_path)) {
+                    Ok(current) if current.size != stat.size || current.mtime != stat.mtime => {
+                        // A single mismatch may just be a write racing the two stats
+                        if rotation_suspected {
+                            file = sftp.open(Path::new(remote_path))?;
+                            offset = 0;
+                            rotation_suspected = false;
+                            updates.push(FollowUpdate::Rotated);
+                            caught_up = false;
+                        } else {
+                            rotation_suspected = true;
+                        }
+                    }
+                    Ok(_) => rotation_suspected = false,
+                    // Moved away and not recreated yet; keep reading the old file
+                    Err(_) => {}
+                }
+            }
+        }
+
+        for update in updates {
+            if tx
+                .send(AppMessage::FollowResult(
+                    id,
+                    remote_path.to_string(),
+                    update,
+                ))
+                .is_err()
+            {
+                return Ok(());
+            }
+        }
+        if caught_up {
+            thread::sleep(POLL_INTERVAL);
+        }
+    }
+    Ok(())
+}
+
+fn read_at(file: &mut File, offset: u64, len: u64) -> anyhow::Result<Vec<u8>> {
+    file.seek(SeekFrom::Start(offset))?;
+    read_up_to(file, len)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_trim_front_cuts_at_line_boundary() {
+        let mut buffer = b"aaaa\nbbbb\ncccc\n".to_vec();
+        assert_eq!(trim_front(&mut buffer, 100), 0);
+
+        // 15 bytes capped to 8: skip past the partial "bbbb" line
+        assert_eq!(trim_front(&mut buffer, 8), 10);
+        assert_eq!(buffer, b"cccc\n");
+
+        // No newline left to cut at: cut exactly at the cap
+        let mut buffer = b"abcdefgh".to_vec();
+        assert_eq!(trim_front(&mut buffer, 3), 5);
+        assert_eq!(buffer, b"fgh");
+    }
+}
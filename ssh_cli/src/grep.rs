@@ -0,0 +1,356 @@
+use regex::Regex;
+use ssh2::Sftp;
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::{Arc, Mutex, mpsc};
+use std::thread;
+
+use crate::app::AppMessage;
+use crate::hex_view::is_binary;
+use crate::model::{ContentHit, EncodingRule, FileEncoding};
+use crate::search_filter::CompiledFilter;
+use crate::ssh::{matches_pattern, open_locked, read_up_to};
+use crate::walk::{WalkGuard, WalkLimit};
+
+/// The search stops after this many hits.
+pub const MAX_HITS: usize = 10_000;
+const CHUNK_SIZE: u64 = 256 * 1024;
+/// Longer lines are cut here so a file without newlines cannot exhaust memory.
+const MAX_LINE_BYTES: usize = 1024 * 1024;
+/// Characters of the matching line kept for the result list.
+const MAX_HIT_CHARS: usize = 500;
+
+/// What to search: regular files under `base_path` passing the search bar
+/// `filter`, scanned line by line with `matcher`.
+pub struct GrepRequest {
+    pub base_path: String,
+    pub filter: CompiledFilter,
+    pub matcher: Regex,
+    /// Encoding rules of the connected host; other files are auto-detected
+    pub rules: Vec<EncodingRule>,
+}
+
+/// A running or finished content search. Dropping it stops the worker.
+pub struct ContentSearch {
+    /// Tells this search's messages apart from those of an earlier one
+    pub id: u64,
+    pub query: String,
+    pub hits: Vec<ContentHit>,
+    pub files_scanned: usize,
+    pub running: bool,
+    /// Why the search ended before covering everything
+    pub limit: Option<String>,
+    cancel: Arc<AtomicBool>,
+}
+
+impl ContentSearch {
+    pub fn start(
+        sftp_arc: Arc<Mutex<Sftp>>,
+        id: u64,
+        query: String,
+        request: GrepRequest,
+        tx: mpsc::Sender<AppMessage>,
+    ) -> Self {
+        let cancel = Arc::new(AtomicBool::new(false));
+        let worker_cancel = cancel.clone();
+        thread::spawn(move || {
+            let result = grep_worker(&sftp_arc, &request, &worker_cancel, id, &tx);
+            let _ = tx.send(AppMessage::ContentSearchFinished(
+                id,
+                result.map_err(|e| e.to_string()),
+            ));
+        });
+        Self {
+            id,
+            query,
+            hits: Vec::new(),
+            files_scanned: 0,
+            running: true,
+            limit: None,
+            cancel,
+        }
+    }
+
+    pub fn stop(&mut self) {
+        self.cancel.store(true, Ordering::SeqCst);
+        self.running = false;
+    }
+}
+
+impl Drop for ContentSearch {
+    fn drop(&mut self) {
+        self.cancel.store(true, Ordering::SeqCst);
+    }
+}
+
+/// Walks the tree depth first in name order. Returns why it ended early, if
+/// it hit MAX_HITS or a walk limit.
+fn grep_worker(
+    sftp_arc: &Arc<Mutex<Sftp>>,
+    request: &GrepRequest,
+    cancel: &AtomicBool,
+    id: u64,
+    tx: &mpsc::Sender<AppMessage>,
+) -> anyhow::Result<Option<String>> {
+    let (mut guard, base) = WalkGuard::start(sftp_arc, &request.base_path, request.filter.walk)?;
+    let mut dirs = vec![base];
+    let mut files_scanned = 0usize;
+    let mut total_hits = 0;
+
+    while let Some(dir) = dirs.pop() {
+        if cancel.load(Ordering::SeqCst) {
+            return Ok(None);
+        }
+        let entries = {
+            let sftp = sftp_arc.lock().map_err(|_| anyhow::anyhow!("Lock error"))?;
+            match sftp.readdir(&dir.path) {
+                Ok(entries) => entries,
+                // Unreadable subdirectories are skipped
+                Err(e) if dir.depth == 1 => return Err(e.into()),
+                Err(_) => continue,
+            }
+        };
+        let mut entries: Vec<_> = entries
+            .into_iter()
+            .filter(|(path, _)| {
+                path.file_name()
+                    .and_then(|n| n.to_str())
+                    .is_some_and(|n| n != "." && n != "..")
+            })
+            .collect();
+        entries.sort_by(|a, b| a.0.cmp(&b.0));
+
+        let mut subdirs = Vec::new();
+        for (path, stat) in entries {
+            if cancel.load(Ordering::SeqCst) {
+                return Ok(None);
+            }
+            let name = path.file_name().and_then(|n| n.to_str()).unwrap_or("");
+            if request.filter.is_excluded(name) {
+                continue;
+            }
+            if !guard.count_entry() {
+                break;
+            }
+            if request.filter.descends_into(dir.depth)
+                && let Some(subdir) = guard.descend(sftp_arc, &dir, &path, &stat)?
+            {
+                subdirs.push(subdir);
+                continue;
+            }
+            if stat.is_dir() {
+                continue;
+            }
+            if !stat.is_file() || !request.filter.matches(name, &stat) {
+                continue;
+            }
+
+            let remote_path = path.to_string_lossy().into_owned();
+            // Unreadable files are skipped
+            let hits = grep_file(
+                sftp_arc,
+                &remote_path,
+                request,
+                cancel,
+                MAX_HITS - total_hits,
+            )
+            .unwrap_or_default();
+            files_scanned += 1;
+            total_hits += hits.len();
+            if !hits.is_empty() || files_scanned.is_multiple_of(50) {
+                tx.send(AppMessage::ContentSearchBatch(id, hits, files_scanned))?;
+            }
+            if total_hits >= MAX_HITS {
+                return Ok(Some(format!("stopped at {} hits", MAX_HITS)));
+            }
+        }
+        if guard.limit == Some(WalkLimit::Entries) {
+            break;
+        }
+        dirs.extend(subdirs.into_iter().rev());
+    }
+    tx.send(AppMessage::ContentSearchBatch(
+        id,
+        Vec::new(),
+        files_scanned,
+    ))?;
+    Ok(guard.limit.map(|limit| limit.to_string()))
+}
+
+/// Streams one file through the matcher, decoding each line with the
+/// encoding the viewer would use. Binary files yield no hits.
+fn grep_file(
+    sftp_arc: &Arc<Mutex<Sftp>>,
+    remote_path: &str,
+    request: &GrepRequest,
+    cancel: &AtomicBool,
+    max_hits: usize,
+) -> anyhow::Result<Vec<ContentHit>> {
+    let (mut file, _) = open_locked(sftp_arc, remote_path)?;
+    let mut chunk = read_up_to(&mut file, CHUNK_SIZE)?;
+    let encoding = request
+        .rules
+        .iter()
+        .find(|r| matches_pattern(remote_path, &r.pattern))
+        .map(|r| r.encoding)
+        .unwrap_or_else(|| FileEncoding::detect(&chunk));
+    let utf16 = matches!(encoding, FileEncoding::Utf16Le | FileEncoding::Utf16Be);
+    if is_binary(&chunk) && !utf16 {
+        return Ok(Vec::new());
+    }
+
+    let mut splitter = LineSplitter::new(encoding);
+    let mut hits = Vec::new();
+    let mut line = 0;
+    let mut check = |offset: u64, bytes: &[u8], hits: &mut Vec<ContentHit>| {
+        line += 1;
+        let text = decode_line(bytes, encoding, offset == 0);
+        if hits.len() < max_hits && request.matcher.is_match(&text) {
+            hits.push(ContentHit {
+                path: remote_path.to_string(),
+                line,
+                offset,
+                text: text.chars().take(MAX_HIT_CHARS).collect(),
+            });
+        }
+    };
+    while !chunk.is_empty() {
+        splitter.feed(&chunk, |offset, bytes| check(offset, bytes, &mut hits));
+        if hits.len() >= max_hits || cancel.load(Ordering::SeqCst) {
+            return Ok(hits);
+        }
+        chunk = read_up_to(&mut file, CHUNK_SIZE)?;
+    }
+    splitter.finish(|offset, bytes| check(offset, bytes, &mut hits));
+    Ok(hits)
+}
+
+fn decode_line(bytes: &[u8], encoding: FileEncoding, first: bool) -> String {
+    let (text, _) = encoding.coder().decode_without_bom_handling(bytes);
+    let text = text.strip_suffix('\r').unwrap_or(&text);
+    let text = if first {
+        text.trim_start_matches('\u{feff}')
+    } else {
+        text
+    };
+    text.to_string()
+}
+
+/// Splits a byte stream into lines, tracking where each line starts. UTF-16
+/// newlines are matched as whole code units.
+struct LineSplitter {
+    newline: &'static [u8],
+    pending: Vec<u8>,
+    /// File offset of `pending[0]`
+    offset: u64,
+    /// Bytes of `pending` already searched for a newline
+    scanned: usize,
+    /// Inside an overlong line whose head was already reported
+    skipping: bool,
+}
+
+impl LineSplitter {
+    fn new(encoding: FileEncoding) -> Self {
+        let newline: &[u8] = match encoding {
+            FileEncoding::Utf16Le => b"\n\0",
+            FileEncoding::Utf16Be => b"\0\n",
+            _ => b"\n",
+        };
+        Self {
+            newline,
+            pending: Vec::new(),
+            offset: 0,
+            scanned: 0,
+            skipping: false,
+        }
+    }
+
+    /// Calls `line` with (offset, bytes without the newline) for each line
+    /// completed by `chunk`.
+    fn feed(&mut self, chunk: &[u8], mut line: impl FnMut(u64, &[u8])) {
+        self.pending.extend_from_slice(chunk);
+        let unit = self.newline.len();
+        let mut start = 0;
+        let mut i = self.scanned;
+        while i + unit <= self.pending.len() {
+            if self.pending[i..i + unit] == *self.newline {
+                if !self.skipping {
+                    line(self.offset + start as u64, &self.pending[start..i]);
+                }
+                self.skipping = false;
+                start = i + unit;
+            }
+            i += unit;
+        }
+        if !self.skipping && self.pending.len() - start > MAX_LINE_BYTES {
+            // Overlong line: report its head now and drop the rest up to the next newline
+            line(
+                self.offset + start as u64,
+                &self.pending[start..start + MAX_LINE_BYTES],
+            );
+            self.skipping = true;
+        }
+        if self.skipping {
+            start = i;
+        }
+        self.pending.drain(..start);
+        self.offset += start as u64;
+        self.scanned = i - start;
+    }
+
+    /// Reports a final line that has no trailing newline.
+    fn finish(&mut self, mut line: impl FnMut(u64, &[u8])) {
+        if !self.skipping && !self.pending.is_empty() {
+            line(self.offset, &self.pending);
+        }
+        self.pending.clear();
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn split(encoding: FileEncoding, chunks: &[&[u8]]) -> Vec<(u64, Vec<u8>)> {
+        let mut splitter = LineSplitter::new(encoding);
+        let mut lines = Vec::new();
+        for chunk in chunks {
+            splitter.feed(chunk, |offset, bytes| lines.push((offset, bytes.to_vec())));
+        }
+        splitter.finish(|offset, bytes| lines.push((offset, bytes.to_vec())));
+        lines
+    }
+
+    #[test]
+    fn test_line_splitter_offsets_across_chunks() {
+        let lines = split(FileEncoding::Utf8, &[b"one\ntw", b"o\n", b"three"]);
+        assert_eq!(
+            lines,
+            vec![
+                (0, b"one".to_vec()),
+                (4, b"two".to_vec()),
+                (8, b"three".to_vec())
+            ]
+        );
+    }
+
+    #[test]
+    fn test_line_splitter_utf16() {
+        // "a\n" then U+0A0A (bytes 0A 0A) which must not count as a newline
+        let data = b"a\0\n\0\x0a\x0a\n\0";
+        let lines = split(FileEncoding::Utf16Le, &[&data[..3], &data[3..]]);
+        assert_eq!(lines.len(), 2);
+        assert_eq!(lines[1], (4, b"\x0a\x0a".to_vec()));
+    }
+
+    #[test]
+    fn test_decode_line() {
+        assert_eq!(
+            decode_line(b"\xef\xbb\xbfhello\r", FileEncoding::Utf8, true),
+            "hello"
+        );
+        assert_eq!(
+            decode_line(b"\x82\xa0", FileEncoding::ShiftJis, false),
+            "あ"
+        );
+    }
+}
@@ -0,0 +1,151 @@
+/// Bytes shown per row of the hex dump.
+pub const BYTES_PER_ROW: usize = 16;
+/// Only the start of the file is inspected when guessing whether it is binary.
+const BINARY_SNIFF_BYTES: usize = 8000;
+
+/// Selection and search state of the hex viewer.
+#[derive(Default)]
+pub struct HexViewState {
+    /// (anchor, cursor) byte offsets; the selection spans both ends inclusive
+    pub selection: Option<(usize, usize)>,
+    pub search_input: String,
+    /// Row to bring into view on the next frame
+    pub scroll_to_row: Option<usize>,
+}
+
+impl HexViewState {
+    pub fn selected_range(&self) -> Option<(usize, usize)> {
+        self.selection
+            .map(|(anchor, cursor)| (anchor.min(cursor), anchor.max(cursor)))
+    }
+
+    pub fn is_selected(&self, offset: usize) -> bool {
+        self.selected_range()
+            .is_some_and(|(start, end)| (start..=end).contains(&offset))
+    }
+
+    /// Click selects a single byte; shift-click extends from the anchor.
+    pub fn click(&mut self, offset: usize, extend: bool) {
+        self.selection = match self.selection {
+            Some((anchor, _)) if extend => Some((anchor, offset)),
+            _ => Some((offset, offset)),
+        };
+    }
+
+    /// Selects the next match of the search pattern after the current selection,
+    /// wrapping to the start. Returns false if the pattern is invalid or absent.
+    pub fn find_next(&mut self, data: &[u8]) -> bool {
+        let Some(pattern) = parse_hex_pattern(&self.search_input) else {
+            return false;
+        };
+        let from = self.selected_range().map_or(0, |(start, _)| start + 1);
+        let Some(found) =
+            find_bytes(data, &pattern, from).or_else(|| find_bytes(data, &pattern, 0))
+        else {
+            return false;
+        };
+        self.selection = Some((found, found + pattern.len() - 1));
+        self.scroll_to_row = Some(found / BYTES_PER_ROW);
+        true
+    }
+}
+
+/// Guesses whether content is binary: a NUL byte, or many control characters
+/// other than the usual whitespace and escape.
+pub fn is_binary(data: &[u8]) -> bool {
+    let sample = &data[..data.len().min(BINARY_SNIFF_BYTES)];
+    if sample.contains(&0) {
+        return true;
+    }
+    let control = sample
+        .iter()
+        .filter(|&&b| b < 0x20 && !matches!(b, b'\t' | b'\n' | b'\r' | 0x0c | 0x1b))
+        .count();
+    control * 10 > sample.len()
+}
+
+/// Parses a pattern such as "7f 45 4c 46" or "7f454c46".
+pub fn parse_hex_pattern(text: &str) -> Option<Vec<u8>> {
+    let digits: String = text.split_whitespace().collect();
+    if digits.is_empty() || !digits.len().is_multiple_of(2) {
+        return None;
+    }
+    (0..digits.len())
+        .step_by(2)
+        .map(|i| u8::from_str_radix(digits.get(i..i + 2)?, 16).ok())
+        .collect()
+}
+
+pub fn find_bytes(haystack: &[u8], needle: &[u8], from: usize) -> Option<usize> {
+    if needle.is_empty() || from >= haystack.len() {
+        return None;
+    }
+    haystack[from..]
+        .windows(needle.len())
+        .position(|w| w == needle)
+        .map(|i| from + i)
+}
+
+/// Character shown in the text column for a byte.
+pub fn printable_char(byte: u8) -> char {
+    if byte.is_ascii_graphic() || byte == b' ' {
+        byte as char
+    } else {
+        '.'
+    }
+}
+
+/// Space-separated hex bytes, e.g. for copying the selection.
+pub fn format_hex_bytes(data: &[u8]) -> String {
+    data.iter()
+        .map(|b| format!("{:02X}", b))
+        .collect::<Vec<_>>()
+        .join(" ")
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_is_binary() {
+        assert!(!is_binary(b"hello\r\n\tworld\n"));
+        assert!(!is_binary("日本語".as_bytes()));
+        assert!(is_binary(b"\x7fELF\x02\x01\x01\x00"));
+        assert!(is_binary(b"\x01\x02\x03abc"));
+        assert!(!is_binary(b""));
+    }
+
+    #[test]
+    fn test_parse_hex_pattern() {
+        assert_eq!(
+            parse_hex_pattern("7f 45 4C 46"),
+            Some(vec![0x7f, 0x45, 0x4c, 0x46])
+        );
+        assert_eq!(
+            parse_hex_pattern("7f454c46"),
+            Some(vec![0x7f, 0x45, 0x4c, 0x46])
+        );
+        assert_eq!(parse_hex_pattern("7f4"), None);
+        assert_eq!(parse_hex_pattern("zz"), None);
+        assert_eq!(parse_hex_pattern(" "), None);
+    }
+
+    #[test]
+    fn test_find_next_wraps() {
+        let data = b"abXYcdXYef";
+        let mut state = HexViewState {
+            search_input: "58 59".to_string(),
+            ..Default::default()
+        };
+        assert!(state.find_next(data));
+        assert_eq!(state.selected_range(), Some((2, 3)));
+        assert!(state.find_next(data));
+        assert_eq!(state.selected_range(), Some((6, 7)));
+        assert!(state.find_next(data));
+        assert_eq!(state.selected_range(), Some((2, 3)));
+
+        state.search_input = "00".to_string();
+        assert!(!state.find_next(data));
+    }
+}
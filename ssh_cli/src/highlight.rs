@@ -0,0 +1,396 @@
+use egui::text::{LayoutJob, LayoutSection, TextFormat};
+use egui::{Color32, FontId, Stroke};
+use std::hash::{DefaultHasher, Hash, Hasher};
+use std::path::Path;
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::{Arc, OnceLock, mpsc};
+use std::thread;
+use syntect::easy::HighlightLines;
+use syntect::highlighting::{FontStyle, Theme, ThemeSet};
+use syntect::parsing::{SyntaxDefinition, SyntaxSet};
+use syntect::util::LinesWithEndings;
+
+/// Larger texts are shown without highlighting so editing stays responsive.
+pub const MAX_HIGHLIGHT_BYTES: usize = 256 * 1024;
+
+const DARK_THEME: &str = "base16-ocean.dark";
+const LIGHT_THEME: &str = "InspiredGitHub";
+
+/// syntect ships no nginx grammar; this covers comments, strings,
+/// variables and directive names.
+const NGINX_SYNTAX: &str = r#"%YAML 1.2
+---
+name: nginx
+file_extensions: [nginx]
+scope: source.nginx
+contexts:
+  main:
+    - match: '#.*$'
+      scope: comment.line.number-sign.nginx
+    - match: '"'
+      push: double_string
+    - match: "'"
+      push: single_string
+    - match: '\$\{?[A-Za-z_][A-Za-z0-9_]*\}?'
+      scope: variable.other.nginx
+    - match: '(?:^|(?<=[{;]))\s*([A-Za-z_][A-Za-z0-9_]*)'
+      captures:
+        1: keyword.other.directive.nginx
+    - match: '\b(on|off)\b'
+      scope: constant.language.nginx
+    - match: '\b\d+[kKmMgGsShHdD]?\b'
+      scope: constant.numeric.nginx
+    - match: '[{};]'
+      scope: punctuation.section.nginx
+  double_string:
+    - meta_scope: string.quoted.double.nginx
+    - match: '\\.'
+      scope: constant.character.escape.nginx
+    - match: '"'
+      pop: true
+  single_string:
+    - meta_scope: string.quoted.single.nginx
+    - match: "'"
+      pop: true
+"#;
+
+struct Highlighter {
+    syntaxes: SyntaxSet,
+    themes: ThemeSet,
+}
+
+/// Built on first use; loading the bundled grammars takes a moment.
+fn highlighter() -> &'static Highlighter {
+    static HIGHLIGHTER: OnceLock<Highlighter> = OnceLock::new();
+    HIGHLIGHTER.get_or_init(|| {
+        let mut builder = SyntaxSet::load_defaults_newlines().into_builder();
+        if let Ok(nginx) = SyntaxDefinition::load_from_str(NGINX_SYNTAX, true, None) {
+            builder.add(nginx);
+        }
+        Highlighter {
+            syntaxes: builder.build(),
+            themes: ThemeSet::load_defaults(),
+        }
+    })
+}
+
+/// Names of all available syntaxes, sorted for the viewer's combo box.
+pub fn syntax_names() -> Vec<String> {
+    let mut names: Vec<String> = highlighter()
+        .syntaxes
+        .syntaxes()
+        .iter()
+        .filter(|s| s.name != "Plain Text")
+        .map(|s| s.name.clone())
+        .collect();
+    names.sort_by_key(|n| n.to_lowercase());
+    names
+}
+
+/// Picks a syntax from the file name, then from a shebang or other first-line
+/// marker. None means plain text.
+pub fn detect_syntax(remote_path: &str, text: &str) -> Option<String> {
+    let syntaxes = &highlighter().syntaxes;
+    let path = Path::new(remote_path);
+    let name = path.file_name()?.to_str()?;
+    let extension = path.extension().and_then(|e| e.to_str());
+
+    let in_nginx_dir = remote_path.contains("/nginx/");
+    let syntax = if name == "nginx.conf" || (in_nginx_dir && extension == Some("conf")) {
+        syntaxes.find_syntax_by_name("nginx")
+    } else {
+        // Full names cover files such as "Makefile" or ".bashrc"
+        extension
+            .and_then(|ext| syntaxes.find_syntax_by_extension(ext))
+            .or_else(|| syntaxes.find_syntax_by_extension(name.trim_start_matches('.')))
+            .or_else(|| syntaxes.find_syntax_by_first_line(text.lines().next()?))
+    };
+    syntax
+        .filter(|s| s.name != "Plain Text")
+        .map(|s| s.name.clone())
+}
+
+/// Highlighted layout of the editor text. Highlighting runs on a worker
+/// thread whenever the text revision, syntax or theme changes; until it
+/// finishes, the previous layout is reused for the unchanged start and end
+/// of the text.
+#[derive(Default)]
+pub struct HighlightCache {
+    key: u64,
+    job: Option<LayoutJob>,
+    worker: Option<HighlightWorker>,
+}
+
+struct HighlightWorker {
+    key: u64,
+    result: mpsc::Receiver<LayoutJob>,
+    cancel: Arc<AtomicBool>,
+}
+
+impl Drop for HighlightWorker {
+    fn drop(&mut self) {
+        self.cancel.store(true, Ordering::SeqCst);
+    }
+}
+
+impl HighlightCache {
+    /// `revision` must change whenever `text` does.
+    pub fn layout(&mut self, ui: &egui::Ui, text: &str, syntax: &str, revision: u64) -> LayoutJob {
+        let font_id = egui::TextStyle::Monospace.resolve(ui.style());
+        let dark = ui.visuals().dark_mode;
+        let text_color = ui.visuals().text_color();
+
+        let mut hasher = DefaultHasher::new();
+        (revision, syntax, dark, font_id.size.to_bits()).hash(&mut hasher);
+        let key = hasher.finish();
+
+        if let Some(worker) = &self.worker
+            && worker.key == key
+            && let Ok(job) = worker.result.try_recv()
+        {
+            self.key = key;
+            self.job = Some(job);
+            self.worker = None;
+        }
+        if self.key == key
+            && let Some(job) = &self.job
+        {
+            return job.clone();
+        }
+
+        // A newer revision replaces (and cancels) a worker that is still running
+        if self.worker.as_ref().is_none_or(|w| w.key != key) {
+            let (tx, result) = mpsc::channel();
+            let cancel = Arc::new(AtomicBool::new(false));
+            let worker_cancel = cancel.clone();
+            let ctx = ui.ctx().clone();
+            let (text, syntax, font_id) = (text.to_owned(), syntax.to_owned(), font_id.clone());
+            thread::spawn(move || {
+                if let Some(job) =
+                    highlight(&text, &syntax, dark, font_id, text_color, &worker_cancel)
+                    && tx.send(job).is_ok()
+                {
+                    ctx.request_repaint();
+                }
+            });
+            self.worker = Some(HighlightWorker {
+                key,
+                result,
+                cancel,
+            });
+        }
+
+        let plain = TextFormat::simple(font_id, text_color);
+        match &self.job {
+            Some(previous) => reuse_layout(previous, text, plain),
+            None => LayoutJob::single_section(text.to_owned(), plain),
+        }
+    }
+}
+
+/// Lays out `text` with the formatting of `previous` for the bytes both share
+/// at the start and at the end; the edited part in between is plain.
+fn reuse_layout(previous: &LayoutJob, text: &str, plain: TextFormat) -> LayoutJob {
+    let old = previous.text.as_str();
+    let mut prefix = old
+        .bytes()
+        .zip(text.bytes())
+        .take_while(|(a, b)| a == b)
+        .count();
+    while !text.is_char_boundary(prefix) {
+        prefix -= 1;
+    }
+    let max_suffix = old.len().min(text.len()) - prefix;
+    let mut suffix = old
+        .bytes()
+        .rev()
+        .zip(text.bytes().rev())
+        .take(max_suffix)
+        .take_while(|(a, b)| a == b)
+        .count();
+    while !text.is_char_boundary(text.len() - suffix) {
+        suffix -= 1;
+    }
+
+    let mut job = LayoutJob {
+        text: text.to_owned(),
+        ..Default::default()
+    };
+    let mut push = |start: usize, end: usize, format: &TextFormat| {
+        if start < end {
+            job.sections.push(LayoutSection {
+                leading_space: 0.0,
+                byte_range: start..end,
+                format: format.clone(),
+            });
+        }
+    };
+    for section in &previous.sections {
+        let range = &section.byte_range;
+        push(range.start, range.end.min(prefix), &section.format);
+    }
+    push(prefix, text.len() - suffix, &plain);
+    let old_suffix_start = old.len() - suffix;
+    for section in &previous.sections {
+        let range = &section.byte_range;
+        let start = range.start.max(old_suffix_start);
+        if start < range.end {
+            push(
+                start + text.len() - old.len(),
+                range.end + text.len() - old.len(),
+                &section.format,
+            );
+        }
+    }
+    job
+}
+
+/// Highlights `text`; returns None if cancelled part way.
+fn highlight(
+    text: &str,
+    syntax: &str,
+    dark: bool,
+    font_id: FontId,
+    color: Color32,
+    cancel: &AtomicBool,
+) -> Option<LayoutJob> {
+    let plain = TextFormat::simple(font_id.clone(), color);
+    let hl = highlighter();
+    let theme = hl
+        .themes
+        .themes
+        .get(if dark { DARK_THEME } else { LIGHT_THEME });
+    let (Some(syntax), Some(theme)) = (hl.syntaxes.find_syntax_by_name(syntax), theme) else {
+        return Some(LayoutJob::single_section(text.to_owned(), plain));
+    };
+
+    let mut lines = HighlightLines::new(syntax, theme);
+    let mut job = LayoutJob::default();
+    for line in LinesWithEndings::from(text) {
+        if cancel.load(Ordering::Relaxed) {
+            return None;
+        }
+        match lines.highlight_line(line, &hl.syntaxes) {
+            Ok(ranges) => {
+                for (style, piece) in ranges {
+                    job.append(piece, 0.0, text_format(style, &font_id, theme));
+                }
+            }
+            Err(_) => job.append(line, 0.0, plain.clone()),
+        }
+    }
+    Some(job)
+}
+
+fn text_format(style: syntect::highlighting::Style, font_id: &FontId, theme: &Theme) -> TextFormat {
+    let fg = style.foreground;
+    let color = Color32::from_rgb(fg.r, fg.g, fg.b);
+    let underline = if style.font_style.contains(FontStyle::UNDERLINE) {
+        Stroke::new(1.0, color)
+    } else {
+        Stroke::NONE
+    };
+    // The theme background is left to the editor; only override it where a
+    // scope sets its own
+    let background = match (style.background, theme.settings.background) {
+        (bg, Some(default)) if bg != default => Color32::from_rgb(bg.r, bg.g, bg.b),
+        _ => Color32::TRANSPARENT,
+    };
+    TextFormat {
+        font_id: font_id.clone(),
+        color,
+        italics: style.font_style.contains(FontStyle::ITALIC),
+        underline,
+        background,
+        ..Default::default()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_detect_syntax() {
+        assert_eq!(
+            detect_syntax("/etc/app/config.yml", "").as_deref(),
+            Some("YAML")
+        );
+        assert_eq!(detect_syntax("/srv/data.json", "").as_deref(), Some("JSON"));
+        assert_eq!(
+            detect_syntax("/usr/local/bin/deploy", "#!/bin/bash\necho hi\n").as_deref(),
+            Some("Bourne Again Shell (bash)")
+        );
+        assert_eq!(
+            detect_syntax("/etc/nginx/nginx.conf", "").as_deref(),
+            Some("nginx")
+        );
+        assert_eq!(
+            detect_syntax("/etc/nginx/conf.d/site.conf", "").as_deref(),
+            Some("nginx")
+        );
+        assert_eq!(
+            detect_syntax("/var/log/syslog", "Oct 18 kernel: boot\n"),
+            None
+        );
+    }
+
+    #[test]
+    fn test_highlight_splits_sections() {
+        let job = highlight(
+            "{\"key\": 1}\n",
+            "JSON",
+            true,
+            FontId::monospace(12.0),
+            Color32::WHITE,
+            &AtomicBool::new(false),
+        )
+        .unwrap();
+        assert_eq!(job.text, "{\"key\": 1}\n");
+        assert!(job.sections.len() > 1);
+
+        let job = highlight(
+            "server {\n  listen 80;\n}\n",
+            "nginx",
+            true,
+            FontId::monospace(12.0),
+            Color32::WHITE,
+            &AtomicBool::new(false),
+        )
+        .unwrap();
+        assert!(job.sections.len() > 1);
+    }
+
+    #[test]
+    fn test_reuse_layout_keeps_unchanged_formatting() {
+        let font_id = FontId::monospace(12.0);
+        let format = |color| TextFormat::simple(font_id.clone(), color);
+        let mut previous = LayoutJob::default();
+        previous.append("key", 0.0, format(Color32::RED));
+        previous.append(": ", 0.0, format(Color32::GRAY));
+        previous.append("value", 0.0, format(Color32::GREEN));
+
+        let job = reuse_layout(&previous, "key: new value", format(Color32::WHITE));
+        let sections: Vec<_> = job
+            .sections
+            .iter()
+            .map(|s| (&job.text[s.byte_range.clone()], s.format.color))
+            .collect();
+        assert_eq!(
+            sections,
+            vec![
+                ("key", Color32::RED),
+                (": ", Color32::GRAY),
+                ("new ", Color32::WHITE),
+                ("value", Color32::GREEN),
+            ]
+        );
+
+        // Multi-byte characters are never split
+        let mut previous = LayoutJob::default();
+        previous.append("é", 0.0, format(Color32::RED));
+        let job = reuse_layout(&previous, "ê", format(Color32::WHITE));
+        assert_eq!(job.sections.len(), 1);
+        assert_eq!(job.sections[0].byte_range, 0..2);
+    }
+}
@@ -0,0 +1,115 @@
+use egui::load::Bytes;
+use std::io::Cursor;
+use std::path::Path;
+
+/// Images are fetched whole; anything larger opens in the regular viewer.
+pub const MAX_IMAGE_BYTES: usize = 32 * 1024 * 1024;
+const MIN_ZOOM: f32 = 0.05;
+const MAX_ZOOM: f32 = 32.0;
+
+const IMAGE_EXTENSIONS: &[&str] = &["png", "jpg", "jpeg", "gif", "webp", "svg"];
+
+/// Decoded-image preview state. `zoom` is None while fitting to the window.
+pub struct ImageViewState {
+    /// Cache key for egui's image loaders; forgotten when the viewer closes
+    pub uri: String,
+    pub bytes: Bytes,
+    pub zoom: Option<f32>,
+    /// Pixel size read from the header (None for SVG)
+    pub size: Option<(u32, u32)>,
+}
+
+impl ImageViewState {
+    pub fn new(remote_path: &str, extension: &str, data: &[u8]) -> Self {
+        // The loaders pick a decoder by extension, and a fresh URI per load
+        // keeps a reopened file from showing a stale cached texture
+        let uri = format!(
+            "bytes://{}/{}.{}",
+            chrono::Utc::now().timestamp_millis(),
+            remote_path.trim_start_matches('/'),
+            extension
+        );
+        let size = image::ImageReader::new(Cursor::new(data))
+            .with_guessed_format()
+            .ok()
+            .and_then(|reader| reader.into_dimensions().ok());
+        Self {
+            uri,
+            bytes: Bytes::from(data.to_vec()),
+            zoom: None,
+            size,
+        }
+    }
+
+    pub fn zoom_by(&mut self, factor: f32) {
+        self.set_zoom(self.zoom.unwrap_or(1.0) * factor);
+    }
+
+    fn set_zoom(&mut self, zoom: f32) {
+        self.zoom = Some(zoom.clamp(MIN_ZOOM, MAX_ZOOM));
+    }
+}
+
+/// Whether the file name suggests an image worth fetching whole.
+pub fn is_image_path(remote_path: &str) -> bool {
+    Path::new(remote_path)
+        .extension()
+        .and_then(|e| e.to_str())
+        .is_some_and(|e| IMAGE_EXTENSIONS.contains(&e.to_lowercase().as_str()))
+}
+
+/// Identifies a supported image by its magic bytes (SVG by name or markup).
+/// Returns the extension used to pick the decoder.
+pub fn image_extension(remote_path: &str, data: &[u8]) -> Option<&'static str> {
+    if data.starts_with(b"\x89PNG\r\n\x1a\n") {
+        return Some("png");
+    }
+    if data.starts_with(&[0xFF, 0xD8, 0xFF]) {
+        return Some("jpg");
+    }
+    if data.starts_with(b"GIF87a") || data.starts_with(b"GIF89a") {
+        return Some("gif");
+    }
+    if data.len() >= 12 && &data[..4] == b"RIFF" && &data[8..12] == b"WEBP" {
+        return Some("webp");
+    }
+
+    let named_svg = remote_path.to_lowercase().ends_with(".svg");
+    let head = String::from_utf8_lossy(&data[..data.len().min(1024)]);
+    let head = head.trim_start_matches('\u{feff}').trim_start();
+    let looks_svg = head.starts_with("<svg")
+        || ((head.starts_with("<?xml") || head.starts_with("<!DOCTYPE svg"))
+            && head.contains("<svg"));
+    (named_svg && head.starts_with('<') || looks_svg).then_some("svg")
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_image_extension() {
+        assert_eq!(image_extension("/a", b"\x89PNG\r\n\x1a\n...."), Some("png"));
+        assert_eq!(image_extension("/a", b"\xFF\xD8\xFF\xE0"), Some("jpg"));
+        assert_eq!(image_extension("/a", b"GIF89a.."), Some("gif"));
+        assert_eq!(image_extension("/a", b"RIFF\0\0\0\0WEBPVP8 "), Some("webp"));
+        assert_eq!(
+            image_extension("/chart", b"<?xml version=\"1.0\"?>\n<svg xmlns=\"\"/>"),
+            Some("svg")
+        );
+        assert_eq!(image_extension("/icon.SVG", b"<svg/>"), Some("svg"));
+        assert_eq!(image_extension("/fake.png", b"not an image"), None);
+        assert_eq!(
+            image_extension("/doc.xml", b"<?xml version=\"1.0\"?><doc/>"),
+            None
+        );
+    }
+
+    #[test]
+    fn test_is_image_path() {
+        assert!(is_image_path("/tmp/shot.PNG"));
+        assert!(is_image_path("chart.svg"));
+        assert!(!is_image_path("/var/log/app.log"));
+        assert!(!is_image_path("/etc/png"));
+    }
+}
@@ -2,6 +2,22 @@
 mod model;
 mod ssh;
 mod app;
+mod external_edit;
+mod paged_view;
+mod follow;
+mod hex_view;
+mod highlight;
+mod find;
+mod image_view;
+mod decompress;
+mod data_view;
+mod grep;
+mod search_filter;
+mod remote_find;
+mod walk;
+mod nav_history;
+mod path_bar;
+mod dir_tree;
 
 use app::SshApp;
 
@@ -4,15 +4,69 @@ pub struct FileEntry {
     pub size: u64,
     pub date: String,
     pub name: String,
+    /// Full remote path; search results come from other directories than the current one
+    pub path: String,
+    pub link: Option<SymlinkInfo>,
+    // Raw stat fields kept for the owner columns and the details pane
+    pub mode: Option<u32>,
+    pub uid: Option<u32>,
+    pub gid: Option<u32>,
+    pub atime: Option<u64>,
+    pub mtime: Option<u64>,
+}
+
+impl FileEntry {
+    /// True for directories and for symlinks that resolve to a directory.
+    pub fn is_dir(&self) -> bool {
+        match &self.link {
+            Some(link) => link.target_is_dir,
+            None => self.perm.starts_with('d'),
+        }
+    }
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct SymlinkInfo {
+    pub target: String,
+    pub target_is_dir: bool,
+    pub broken: bool,
 }
 
 #[derive(PartialEq, Clone, Copy)]
 pub enum SortColumn {
     None,
     Permission,
+    Owner,
+    Group,
     Size,
     Date,
     Name,
+    Path,
+}
+
+/// uid/gid to name tables read from the remote /etc/passwd and /etc/group.
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct IdNames {
+    pub users: HashMap<u32, String>,
+    pub groups: HashMap<u32, String>,
+}
+
+impl IdNames {
+    pub fn user_name(&self, uid: Option<u32>) -> String {
+        Self::lookup(&self.users, uid)
+    }
+
+    pub fn group_name(&self, gid: Option<u32>) -> String {
+        Self::lookup(&self.groups, gid)
+    }
+
+    // Falls back to the numeric id when the name is unknown
+    fn lookup(table: &HashMap<u32, String>, id: Option<u32>) -> String {
+        match id {
+            Some(id) => table.get(&id).cloned().unwrap_or_else(|| id.to_string()),
+            None => "-".to_string(),
+        }
+    }
 }
 
 #[derive(PartialEq, Clone, Copy)]
@@ -22,6 +76,9 @@ pub enum SortDirection {
 }
 
 use serde::{Deserialize, Serialize};
+use std::collections::HashMap;
+use crate::find::FindMode;
+use crate::search_filter::SearchFilter;
 
 #[derive(Debug, Clone, Serialize, Deserialize, PartialEq)]
 pub struct FavoriteConnection {
@@ -39,18 +96,585 @@ pub struct DirectoryBookmark {
     pub host: String,
 }
 
-#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+/// Searches kept in the recent-search history.
+pub const MAX_SEARCH_HISTORY: usize = 20;
+
+/// Everything needed to run a search again: where, on which host, and the
+/// search bar contents.
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq)]
+pub struct SearchQuery {
+    pub host: String,
+    pub base_path: String,
+    pub filter: SearchFilter,
+    /// Text to find inside files; empty for a name search
+    #[serde(default)]
+    pub content_query: String,
+    #[serde(default = "default_content_mode")]
+    pub content_mode: FindMode,
+}
+
+fn default_content_mode() -> FindMode {
+    FindMode::IgnoreCase
+}
+
+impl SearchQuery {
+    /// Short description for menus, e.g. `*.hprof containing "OutOfMemory" in /var/app`.
+    pub fn label(&self) -> String {
+        let mut label = match self.filter.pattern.trim() {
+            "" => "(filters)".to_string(),
+            pattern => pattern.to_string(),
+        };
+        if !self.content_query.is_empty() {
+            label.push_str(&format!(" containing \"{}\"", self.content_query));
+        }
+        format!("{} in {}", label, self.base_path)
+    }
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq)]
+pub struct SavedSearch {
+    pub name: String,
+    pub query: SearchQuery,
+}
+
+/// Puts `query` at the front of the history, dropping an identical older
+/// entry and the oldest ones beyond MAX_SEARCH_HISTORY.
+pub fn push_search_history(history: &mut Vec<SearchQuery>, query: SearchQuery) {
+    history.retain(|q| *q != query);
+    history.insert(0, query);
+    history.truncate(MAX_SEARCH_HISTORY);
+}
+
+/// Progress of the background line indexer for the paged viewer.
+#[derive(Debug, Clone, PartialEq)]
+pub struct LineIndexUpdate {
+    /// New checkpoints (start offsets of every LINE_INDEX_STRIDE-th line)
+    pub checkpoints: Vec<u64>,
+    pub scanned_bytes: u64,
+    /// Set once the whole file has been scanned
+    pub total_lines: Option<u64>,
+}
+
+/// A line matched by the content search.
+#[derive(Debug, Clone, PartialEq)]
+pub struct ContentHit {
+    pub path: String,
+    /// 1-based line number
+    pub line: u64,
+    /// Byte offset of the line start, for opening large files in the paged viewer
+    pub offset: u64,
+    pub text: String,
+}
+
+/// Events from the live tail (follow) worker.
+#[derive(Debug, Clone, PartialEq)]
+pub enum FollowUpdate {
+    /// Bytes appended to the file
+    Data(Vec<u8>),
+    /// The file shrank; reading restarts from the beginning
+    Truncated,
+    /// The path now names a different file; the new file is followed from the start
+    Rotated,
+    /// Polling stopped because of an error
+    Error(String),
+}
+
+/// Application settings persisted to settings.json.
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq)]
+#[serde(default)]
+pub struct AppSettings {
+    /// Command used by "Edit externally"; extra arguments are allowed (e.g. `code --wait`)
+    /// and parts containing spaces can be quoted
+    pub external_editor: String,
+    /// Newest first; the first matching rule wins
+    pub encoding_rules: Vec<EncodingRule>,
+    /// Search with `find` over an exec channel when the account has a shell
+    pub server_side_find: bool,
+}
+
+impl Default for AppSettings {
+    fn default() -> Self {
+        let external_editor = std::env::var("VISUAL")
+            .or_else(|_| std::env::var("EDITOR"))
+            .unwrap_or_else(|_| {
+                if cfg!(windows) {
+                    "notepad.exe".to_string()
+                } else {
+                    "xdg-open".to_string()
+                }
+            });
+        Self {
+            external_editor,
+            encoding_rules: Vec::new(),
+            server_side_find: true,
+        }
+    }
+}
+
+/// Text encodings offered by the viewer (everything `encoding_rs` supports
+/// except the internal "replacement" encoding).
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
 pub enum FileEncoding {
     Utf8,
+    Utf16Le,
+    Utf16Be,
     ShiftJis,
+    EucJp,
+    Iso2022Jp,
+    Gbk,
+    Gb18030,
+    Big5,
+    EucKr,
+    Windows874,
+    Windows1250,
+    Windows1251,
+    Windows1252,
+    Windows1253,
+    Windows1254,
+    Windows1255,
+    Windows1256,
+    Windows1257,
+    Windows1258,
+    Iso8859_2,
+    Iso8859_3,
+    Iso8859_4,
+    Iso8859_5,
+    Iso8859_6,
+    Iso8859_7,
+    Iso8859_8,
+    Iso8859_8I,
+    Iso8859_10,
+    Iso8859_13,
+    Iso8859_14,
+    Iso8859_15,
+    Iso8859_16,
+    Ibm866,
+    Koi8R,
+    Koi8U,
+    Macintosh,
+    MacCyrillic,
+    UserDefined,
+}
+
+impl FileEncoding {
+    pub const ALL: &'static [FileEncoding] = &[
+        FileEncoding::Utf8,
+        FileEncoding::Utf16Le,
+        FileEncoding::Utf16Be,
+        FileEncoding::ShiftJis,
+        FileEncoding::EucJp,
+        FileEncoding::Iso2022Jp,
+        FileEncoding::Gbk,
+        FileEncoding::Gb18030,
+        FileEncoding::Big5,
+        FileEncoding::EucKr,
+        FileEncoding::Windows874,
+        FileEncoding::Windows1250,
+        FileEncoding::Windows1251,
+        FileEncoding::Windows1252,
+        FileEncoding::Windows1253,
+        FileEncoding::Windows1254,
+        FileEncoding::Windows1255,
+        FileEncoding::Windows1256,
+        FileEncoding::Windows1257,
+        FileEncoding::Windows1258,
+        FileEncoding::Iso8859_2,
+        FileEncoding::Iso8859_3,
+        FileEncoding::Iso8859_4,
+        FileEncoding::Iso8859_5,
+        FileEncoding::Iso8859_6,
+        FileEncoding::Iso8859_7,
+        FileEncoding::Iso8859_8,
+        FileEncoding::Iso8859_8I,
+        FileEncoding::Iso8859_10,
+        FileEncoding::Iso8859_13,
+        FileEncoding::Iso8859_14,
+        FileEncoding::Iso8859_15,
+        FileEncoding::Iso8859_16,
+        FileEncoding::Ibm866,
+        FileEncoding::Koi8R,
+        FileEncoding::Koi8U,
+        FileEncoding::Macintosh,
+        FileEncoding::MacCyrillic,
+        FileEncoding::UserDefined,
+    ];
+
+    pub fn coder(&self) -> &'static encoding_rs::Encoding {
+        match self {
+            FileEncoding::Utf8 => encoding_rs::UTF_8,
+            FileEncoding::Utf16Le => encoding_rs::UTF_16LE,
+            FileEncoding::Utf16Be => encoding_rs::UTF_16BE,
+            FileEncoding::ShiftJis => encoding_rs::SHIFT_JIS,
+            FileEncoding::EucJp => encoding_rs::EUC_JP,
+            FileEncoding::Iso2022Jp => encoding_rs::ISO_2022_JP,
+            FileEncoding::Gbk => encoding_rs::GBK,
+            FileEncoding::Gb18030 => encoding_rs::GB18030,
+            FileEncoding::Big5 => encoding_rs::BIG5,
+            FileEncoding::EucKr => encoding_rs::EUC_KR,
+            FileEncoding::Windows874 => encoding_rs::WINDOWS_874,
+            FileEncoding::Windows1250 => encoding_rs::WINDOWS_1250,
+            FileEncoding::Windows1251 => encoding_rs::WINDOWS_1251,
+            FileEncoding::Windows1252 => encoding_rs::WINDOWS_1252,
+            FileEncoding::Windows1253 => encoding_rs::WINDOWS_1253,
+            FileEncoding::Windows1254 => encoding_rs::WINDOWS_1254,
+            FileEncoding::Windows1255 => encoding_rs::WINDOWS_1255,
+            FileEncoding::Windows1256 => encoding_rs::WINDOWS_1256,
+            FileEncoding::Windows1257 => encoding_rs::WINDOWS_1257,
+            FileEncoding::Windows1258 => encoding_rs::WINDOWS_1258,
+            FileEncoding::Iso8859_2 => encoding_rs::ISO_8859_2,
+            FileEncoding::Iso8859_3 => encoding_rs::ISO_8859_3,
+            FileEncoding::Iso8859_4 => encoding_rs::ISO_8859_4,
+            FileEncoding::Iso8859_5 => encoding_rs::ISO_8859_5,
+            FileEncoding::Iso8859_6 => encoding_rs::ISO_8859_6,
+            FileEncoding::Iso8859_7 => encoding_rs::ISO_8859_7,
+            FileEncoding::Iso8859_8 => encoding_rs::ISO_8859_8,
+            FileEncoding::Iso8859_8I => encoding_rs::ISO_8859_8_I,
+            FileEncoding::Iso8859_10 => encoding_rs::ISO_8859_10,
+            FileEncoding::Iso8859_13 => encoding_rs::ISO_8859_13,
+            FileEncoding::Iso8859_14 => encoding_rs::ISO_8859_14,
+            FileEncoding::Iso8859_15 => encoding_rs::ISO_8859_15,
+            FileEncoding::Iso8859_16 => encoding_rs::ISO_8859_16,
+            FileEncoding::Ibm866 => encoding_rs::IBM866,
+            FileEncoding::Koi8R => encoding_rs::KOI8_R,
+            FileEncoding::Koi8U => encoding_rs::KOI8_U,
+            FileEncoding::Macintosh => encoding_rs::MACINTOSH,
+            FileEncoding::MacCyrillic => encoding_rs::X_MAC_CYRILLIC,
+            FileEncoding::UserDefined => encoding_rs::X_USER_DEFINED,
+        }
+    }
+
+    pub fn from_coder(coder: &'static encoding_rs::Encoding) -> Option<FileEncoding> {
+        Self::ALL.iter().copied().find(|e| e.coder() == coder)
+    }
+
+    /// Encodes `text` for saving. Returns the bytes and whether any character
+    /// could not be represented.
+    pub fn encode(&self, text: &str) -> (Vec<u8>, bool) {
+        // encoding_rs only decodes UTF-16; its encoder would emit UTF-8
+        match self {
+            FileEncoding::Utf16Le => (text.encode_utf16().flat_map(u16::to_le_bytes).collect(), false),
+            FileEncoding::Utf16Be => (text.encode_utf16().flat_map(u16::to_be_bytes).collect(), false),
+            _ => {
+                let (encoded, _, had_errors) = self.coder().encode(text);
+                (encoded.into_owned(), had_errors)
+            }
+        }
+    }
+
+    /// Byte order mark written on save when the file had one.
+    pub fn bom(&self) -> Option<&'static [u8]> {
+        match self {
+            FileEncoding::Utf8 => Some(b"\xEF\xBB\xBF"),
+            FileEncoding::Utf16Le => Some(b"\xFF\xFE"),
+            FileEncoding::Utf16Be => Some(b"\xFE\xFF"),
+            _ => None,
+        }
+    }
+
+    /// Guesses the encoding of file content: BOM first, then UTF-16 without a
+    /// BOM (zero high bytes), then ISO-2022-JP escapes, then UTF-8 validity,
+    /// then chardetng's heuristics.
+    pub fn detect(data: &[u8]) -> FileEncoding {
+        const SAMPLE_BYTES: usize = 64 * 1024;
+
+        if let Some((coder, _)) = encoding_rs::Encoding::for_bom(data) {
+            return Self::from_coder(coder).unwrap_or(FileEncoding::Utf8);
+        }
+        let sample = &data[..data.len().min(SAMPLE_BYTES)];
+        if let Some(utf16) = detect_utf16_without_bom(sample) {
+            return utf16;
+        }
+        // ISO-2022-JP is 7-bit, so it would otherwise pass as UTF-8
+        if has_iso_2022_jp_escape(sample) {
+            return FileEncoding::Iso2022Jp;
+        }
+        match std::str::from_utf8(sample) {
+            Ok(_) => return FileEncoding::Utf8,
+            // A multi-byte sequence cut off by the sample limit is still UTF-8
+            Err(e) if e.error_len().is_none() => return FileEncoding::Utf8,
+            Err(_) => {}
+        }
+
+        let mut detector = chardetng::EncodingDetector::new();
+        detector.feed(sample, sample.len() == data.len());
+        Self::from_coder(detector.guess(None, true)).unwrap_or(FileEncoding::Utf8)
+    }
+}
+
+/// Looks for the escape sequences that switch ISO-2022-JP into a Japanese
+/// character set. `ESC ( B` (back to ASCII) alone is not enough: terminal
+/// output such as `tput sgr0` contains it in plain ASCII logs.
+fn has_iso_2022_jp_escape(sample: &[u8]) -> bool {
+    const DESIGNATIONS: [&[u8]; 4] = [b"\x1b$B", b"\x1b$@", b"\x1b(J", b"\x1b(I"];
+    sample
+        .windows(3)
+        .any(|window| DESIGNATIONS.contains(&window))
+}
+
+/// Mostly-ASCII UTF-16 text has a zero byte in every other position.
+fn detect_utf16_without_bom(sample: &[u8]) -> Option<FileEncoding> {
+    let pairs = sample.len() / 2;
+    if pairs < 2 {
+        return None;
+    }
+    let even_zeros = sample.iter().step_by(2).filter(|&&b| b == 0).count();
+    let odd_zeros = sample.iter().skip(1).step_by(2).filter(|&&b| b == 0).count();
+    if odd_zeros * 10 >= pairs * 4 && even_zeros * 10 < pairs {
+        Some(FileEncoding::Utf16Le)
+    } else if even_zeros * 10 >= pairs * 4 && odd_zeros * 10 < pairs {
+        Some(FileEncoding::Utf16Be)
+    } else {
+        None
+    }
 }
 
 impl std::fmt::Display for FileEncoding {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "{}", self.coder().name())
+    }
+}
+
+/// Line-ending style of a text file. The editor works on "\n" only and
+/// converts back on save; `Mixed` content is edited and saved as-is.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum LineEnding {
+    Lf,
+    CrLf,
+    Cr,
+    Mixed,
+}
+
+impl LineEnding {
+    /// Styles a file can be converted to
+    pub const CONVERTIBLE: [LineEnding; 3] = [LineEnding::Lf, LineEnding::CrLf, LineEnding::Cr];
+
+    /// Detects the style; text without line breaks counts as LF.
+    pub fn detect(text: &str) -> LineEnding {
+        let bytes = text.as_bytes();
+        let (mut lf, mut crlf, mut cr) = (false, false, false);
+        let mut i = 0;
+        while i < bytes.len() {
+            match bytes[i] {
+                b'\r' if bytes.get(i + 1) == Some(&b'\n') => {
+                    crlf = true;
+                    i += 1;
+                }
+                b'\r' => cr = true,
+                b'\n' => lf = true,
+                _ => {}
+            }
+            i += 1;
+        }
+        match (lf, crlf, cr) {
+            (_, false, false) => LineEnding::Lf,
+            (false, true, false) => LineEnding::CrLf,
+            (false, false, true) => LineEnding::Cr,
+            _ => LineEnding::Mixed,
+        }
+    }
+
+    /// Replaces every line break with "\n".
+    pub fn normalize(text: &str) -> String {
+        text.replace("\r\n", "\n").replace('\r', "\n")
+    }
+
+    /// Converts "\n"-separated editor text to this style for saving.
+    pub fn apply(&self, text: &str) -> String {
+        match self {
+            LineEnding::Lf | LineEnding::Mixed => text.to_string(),
+            LineEnding::CrLf => text.replace('\n', "\r\n"),
+            LineEnding::Cr => text.replace('\n', "\r"),
+        }
+    }
+}
+
+impl std::fmt::Display for LineEnding {
     fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
         match self {
-            FileEncoding::Utf8 => write!(f, "UTF-8"),
-            FileEncoding::ShiftJis => write!(f, "Shift-JIS"),
+            LineEnding::Lf => write!(f, "LF"),
+            LineEnding::CrLf => write!(f, "CRLF"),
+            LineEnding::Cr => write!(f, "CR"),
+            LineEnding::Mixed => write!(f, "Mixed"),
         }
     }
 }
 
+/// Remembered viewer encoding for files on `host` whose full path matches
+/// the wildcard `pattern` (e.g. `/var/log/app/*.log`).
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct EncodingRule {
+    pub host: String,
+    pub pattern: String,
+    pub encoding: FileEncoding,
+}
+
+/// How the file viewer presents the loaded bytes.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum ViewMode {
+    Text,
+    Hex,
+    Image,
+    Json,
+    Table,
+}
+
+/// Attribute changes requested from the properties dialog.
+/// `dir_mode` is applied to directories and `file_mode` to everything else
+/// when recursing; the target itself uses the mode matching its type.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct PermissionChange {
+    pub dir_mode: u32,
+    pub file_mode: u32,
+    pub uid: Option<u32>,
+    pub gid: Option<u32>,
+    pub recursive: bool,
+}
+
+impl PermissionChange {
+    /// An owner is only sent when its field was edited. The dialog fills
+    /// the fields from the target, so sending them unchanged would chown
+    /// every entry of a recursive chmod to the target's owner.
+    pub fn new(
+        dir_mode: u32,
+        file_mode: u32,
+        uid: &OwnerField,
+        gid: &OwnerField,
+        recursive: bool,
+    ) -> Result<Self, String> {
+        Ok(Self {
+            dir_mode,
+            file_mode,
+            uid: uid.edited().map_err(|_| format!("Invalid UID: {}", uid.input))?,
+            gid: gid.edited().map_err(|_| format!("Invalid GID: {}", gid.input))?,
+            recursive,
+        })
+    }
+}
+
+/// UID or GID text field of the properties dialog, with the value it opened with.
+#[derive(Debug, Clone, Default)]
+pub struct OwnerField {
+    pub input: String,
+    original: String,
+}
+
+impl OwnerField {
+    pub fn new(id: Option<u32>) -> Self {
+        let text = id.map(|id| id.to_string()).unwrap_or_default();
+        Self {
+            input: text.clone(),
+            original: text,
+        }
+    }
+
+    /// The ID to set, or None when the field is untouched or empty.
+    pub fn edited(&self) -> Result<Option<u32>, std::num::ParseIntError> {
+        let input = self.input.trim();
+        if input.is_empty() || input == self.original {
+            return Ok(None);
+        }
+        input.parse().map(Some)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_untouched_owner_fields_are_not_sent() {
+        let (uid, mut gid) = (OwnerField::new(Some(1000)), OwnerField::new(Some(100)));
+        let change = PermissionChange::new(0o755, 0o644, &uid, &gid, true).unwrap();
+        assert_eq!((change.uid, change.gid), (None, None));
+
+        gid.input = " 50 ".to_string();
+        let change = PermissionChange::new(0o755, 0o644, &uid, &gid, true).unwrap();
+        assert_eq!((change.uid, change.gid), (None, Some(50)));
+
+        gid.input = "staff".to_string();
+        assert_eq!(
+            PermissionChange::new(0o755, 0o644, &uid, &gid, true).unwrap_err(),
+            "Invalid GID: staff"
+        );
+    }
+
+    #[test]
+    fn test_detect_encoding() {
+        assert_eq!(FileEncoding::detect(b"plain ascii\n"), FileEncoding::Utf8);
+        assert_eq!(FileEncoding::detect("日本語のテキスト".as_bytes()), FileEncoding::Utf8);
+        assert_eq!(FileEncoding::detect(b"\xff\xfeh\x00i\x00"), FileEncoding::Utf16Le);
+        assert_eq!(FileEncoding::detect(b"\xfe\xff\x00h\x00i"), FileEncoding::Utf16Be);
+        assert_eq!(FileEncoding::detect(b"h\x00e\x00l\x00l\x00o\x00"), FileEncoding::Utf16Le);
+
+        let (sjis, _, _) = encoding_rs::SHIFT_JIS.encode("これは日本語のテキストです。ファイルの文字コードを判定します。");
+        assert_eq!(FileEncoding::detect(&sjis), FileEncoding::ShiftJis);
+        let (euc, _, _) = encoding_rs::EUC_JP.encode("これは日本語のテキストです。ファイルの文字コードを判定します。");
+        assert_eq!(FileEncoding::detect(&euc), FileEncoding::EucJp);
+        let (jis, _, _) = encoding_rs::ISO_2022_JP.encode("これは日本語のテキストです。");
+        assert!(jis.is_ascii());
+        assert_eq!(FileEncoding::detect(&jis), FileEncoding::Iso2022Jp);
+        assert_eq!(FileEncoding::detect(b"\x1b(B\x1b[mdone\n"), FileEncoding::Utf8);
+    }
+
+    #[test]
+    fn test_encode_utf16_round_trip() {
+        let (bytes, had_errors) = FileEncoding::Utf16Le.encode("hé");
+        assert!(!had_errors);
+        assert_eq!(bytes, b"h\x00\xe9\x00");
+        assert_eq!(FileEncoding::Utf16Be.encode("h").0, b"\x00h");
+
+        let (bytes, had_errors) = FileEncoding::ShiftJis.encode("日本");
+        assert!(!had_errors);
+        assert_eq!(encoding_rs::SHIFT_JIS.decode(&bytes).0, "日本");
+    }
+
+    #[test]
+    fn test_line_ending_detect_and_apply() {
+        assert_eq!(LineEnding::detect("a\nb\n"), LineEnding::Lf);
+        assert_eq!(LineEnding::detect("a\r\nb\r\n"), LineEnding::CrLf);
+        assert_eq!(LineEnding::detect("a\rb"), LineEnding::Cr);
+        assert_eq!(LineEnding::detect("a\r\nb\n"), LineEnding::Mixed);
+        assert_eq!(LineEnding::detect("single line"), LineEnding::Lf);
+
+        let text = LineEnding::normalize("a\r\nb\r\n");
+        assert_eq!(text, "a\nb\n");
+        assert_eq!(LineEnding::CrLf.apply(&text), "a\r\nb\r\n");
+        assert_eq!(LineEnding::Cr.apply(&text), "a\rb\r");
+        assert_eq!(LineEnding::Mixed.apply("a\r\nb\n"), "a\r\nb\n");
+    }
+
+    #[test]
+    fn test_encoding_names() {
+        assert_eq!(FileEncoding::ShiftJis.to_string(), "Shift_JIS");
+        assert_eq!(FileEncoding::from_coder(encoding_rs::GBK), Some(FileEncoding::Gbk));
+        assert_eq!(FileEncoding::from_coder(encoding_rs::REPLACEMENT), None);
+    }
+
+    #[test]
+    fn test_search_history() {
+        let query = |pattern: &str| SearchQuery {
+            host: "10.0.0.1:22".to_string(),
+            base_path: "/var/app".to_string(),
+            filter: SearchFilter { pattern: pattern.to_string(), ..Default::default() },
+            content_query: String::new(),
+            content_mode: FindMode::IgnoreCase,
+        };
+        let mut history = Vec::new();
+        for i in 0..MAX_SEARCH_HISTORY + 5 {
+            push_search_history(&mut history, query(&format!("*.{}", i)));
+        }
+        assert_eq!(history.len(), MAX_SEARCH_HISTORY);
+        // Running an older search again moves it to the front without duplicating it
+        push_search_history(&mut history, query("*.10"));
+        assert_eq!(history.len(), MAX_SEARCH_HISTORY);
+        assert_eq!(history[0].label(), "*.10 in /var/app");
+        assert_eq!(history.iter().filter(|q| q.filter.pattern == "*.10").count(), 1);
+
+        // Files written before a field existed still load
+        let saved: SavedSearch = serde_json::from_str(
+            r#"{"name": "dumps", "query": {"host": "h", "base_path": "/", "filter": {"pattern": "core.*"}}}"#,
+        ).unwrap();
+        assert_eq!(saved.query.content_mode, FindMode::IgnoreCase);
+        assert!(!saved.query.filter.recursive);
+    }
+}
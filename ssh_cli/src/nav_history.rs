@@ -0,0 +1,166 @@
+/// Visited directories kept for Back/Forward.
+const MAX_ENTRIES: usize = 100;
+/// Locations offered in the recent-locations dropdown.
+const MAX_RECENT: usize = 15;
+
+/// Back/Forward history of the directories listed in this session.
+/// Back and Forward only move once the target has been listed, so a
+/// listing that fails or is overtaken leaves the position where it was.
+#[derive(Debug, Default)]
+pub struct NavHistory {
+    entries: Vec<String>,
+    /// Position of the current directory in `entries`
+    index: usize,
+    /// Entry that Back/Forward is listing
+    pending: Option<usize>,
+    /// Distinct locations, most recently visited first
+    recent: Vec<String>,
+}
+
+impl NavHistory {
+    /// Records a directory once it has been listed. Arriving at the entry
+    /// Back/Forward went to, or relisting the current one, changes nothing
+    /// else; a new one drops the forward entries.
+    pub fn visit(&mut self, path: &str) {
+        let path = normalize(path);
+        self.recent.retain(|p| *p != path);
+        self.recent.insert(0, path.clone());
+        self.recent.truncate(MAX_RECENT);
+
+        if let Some(pending) = self.pending.take()
+            && self.entries[pending] == path
+        {
+            self.index = pending;
+            return;
+        }
+        if self.entries.get(self.index) == Some(&path) {
+            return;
+        }
+        if !self.entries.is_empty() {
+            self.entries.truncate(self.index + 1);
+        }
+        self.entries.push(path);
+        if self.entries.len() > MAX_ENTRIES {
+            self.entries.remove(0);
+        }
+        self.index = self.entries.len() - 1;
+    }
+
+    /// Forgets the pending Back/Forward target after its listing failed.
+    pub fn cancel_pending(&mut self) {
+        self.pending = None;
+    }
+
+    /// Where Back/Forward step from: the target still being listed, if any.
+    fn position(&self) -> usize {
+        self.pending.unwrap_or(self.index)
+    }
+
+    pub fn can_go_back(&self) -> bool {
+        self.position() > 0
+    }
+
+    pub fn can_go_forward(&self) -> bool {
+        self.position() + 1 < self.entries.len()
+    }
+
+    /// Returns the directory to list for one step back.
+    pub fn back(&mut self) -> Option<String> {
+        if !self.can_go_back() {
+            return None;
+        }
+        let target = self.position() - 1;
+        self.pending = Some(target);
+        Some(self.entries[target].clone())
+    }
+
+    pub fn forward(&mut self) -> Option<String> {
+        if !self.can_go_forward() {
+            return None;
+        }
+        let target = self.position() + 1;
+        self.pending = Some(target);
+        Some(self.entries[target].clone())
+    }
+
+    /// Entries behind the current one, nearest first (for the Back button's tooltip).
+    pub fn back_entries(&self) -> impl Iterator<Item = &String> {
+        self.entries[..self.position()].iter().rev()
+    }
+
+    pub fn recent(&self) -> &[String] {
+        &self.recent
+    }
+
+    pub fn clear(&mut self) {
+        *self = Self::default();
+    }
+}
+
+/// "/var/log/" and "/var/log" are the same place.
+fn normalize(path: &str) -> String {
+    match path.trim_end_matches('/') {
+        "" if path.starts_with('/') => "/".to_string(),
+        trimmed => trimmed.to_string(),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_back_and_forward() {
+        let mut history = NavHistory::default();
+        for path in ["/home/user", "/var", "/var/log/"] {
+            history.visit(path);
+        }
+        assert!(!history.can_go_forward());
+        assert_eq!(history.back().as_deref(), Some("/var"));
+        // Listing the directory Back went to does not add an entry
+        history.visit("/var");
+        assert_eq!(history.back().as_deref(), Some("/home/user"));
+        assert_eq!(history.back(), None);
+        assert_eq!(history.forward().as_deref(), Some("/var"));
+
+        // Going somewhere new drops the forward entries
+        history.visit("/etc");
+        assert!(!history.can_go_forward());
+        assert_eq!(
+            history.back_entries().collect::<Vec<_>>(),
+            ["/var", "/home/user"]
+        );
+        assert_eq!(history.recent(), ["/etc", "/var", "/var/log", "/home/user"]);
+    }
+
+    #[test]
+    fn test_back_waits_for_the_listing() {
+        let mut history = NavHistory::default();
+        for path in ["/a", "/b", "/c"] {
+            history.visit(path);
+        }
+        // Two quick clicks step back twice before either listing finishes
+        assert_eq!(history.back().as_deref(), Some("/b"));
+        assert_eq!(history.back().as_deref(), Some("/a"));
+        history.visit("/a");
+        assert_eq!(history.forward().as_deref(), Some("/b"));
+
+        // A failed listing leaves the position and Recent untouched
+        history.cancel_pending();
+        assert_eq!(history.recent()[0], "/a");
+        assert_eq!(history.forward().as_deref(), Some("/b"));
+
+        // Navigating elsewhere before the Back target lists is a new visit
+        history.visit("/d");
+        assert!(!history.can_go_forward());
+        assert_eq!(history.back_entries().collect::<Vec<_>>(), ["/a"]);
+    }
+
+    #[test]
+    fn test_normalize() {
+        assert_eq!(normalize("/"), "/");
+        assert_eq!(normalize("//"), "/");
+        assert_eq!(normalize("/srv/"), "/srv");
+        assert_eq!(normalize("relative"), "relative");
+    }
+}
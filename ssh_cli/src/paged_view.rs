@@ -0,0 +1,553 @@
+use std::collections::{HashMap, HashSet};
+use std::ops::Range;
+use std::sync::Arc;
+use std::sync::atomic::{AtomicBool, Ordering};
+
+use crate::model::LineIndexUpdate;
+
+/// Size of one byte range fetched over SFTP.
+pub const PAGE_SIZE: u64 = 64 * 1024;
+/// The line index records the start offset of every N-th line.
+pub const LINE_INDEX_STRIDE: u64 = 1000;
+/// Lines longer than this are split into several display rows.
+const MAX_ROW_BYTES: u64 = 16 * 1024;
+/// Keeps memory bounded for multi-GB files (64 pages = 4 MiB).
+const MAX_CACHED_PAGES: usize = 64;
+/// Line numbers are only computed when the checkpoint is this close.
+const MAX_LINE_COUNT_BYTES: u64 = 16 * PAGE_SIZE;
+
+/// One row of the paged viewer. `line_number` is 1-based and only set on
+/// rows that start a line whose number is known.
+pub struct DisplayRow {
+    pub offset: u64,
+    pub bytes: Vec<u8>,
+    pub line_number: Option<u64>,
+}
+
+/// Read-only view over a remote file that is fetched page by page on demand.
+/// Missing pages are queued in `requested` and filled in by `insert_page`;
+/// pages whose read failed stay blank until `retry_failed`.
+pub struct PagedFile {
+    pub size: u64,
+    pub top_offset: u64,
+    pages: HashMap<u64, Vec<u8>>,
+    pending: HashSet<u64>,
+    failed: HashSet<u64>,
+    requested: Vec<u64>,
+    pending_scroll: i64,
+    align_top: bool,
+    line_index: Vec<u64>,
+    pub indexed_bytes: u64,
+    pub total_lines: Option<u64>,
+    /// Current find match, highlighted in the rows
+    pub found: Option<Range<u64>>,
+    /// Set when the viewer is dropped so the background indexer stops
+    pub cancel: Arc<AtomicBool>,
+}
+
+impl Drop for PagedFile {
+    fn drop(&mut self) {
+        self.cancel.store(true, Ordering::SeqCst);
+    }
+}
+
+impl PagedFile {
+    /// `initial` holds the first bytes of the file, already read by the viewer.
+    pub fn new(size: u64, initial: &[u8]) -> Self {
+        let mut paged = Self {
+            size,
+            top_offset: 0,
+            pages: HashMap::new(),
+            pending: HashSet::new(),
+            failed: HashSet::new(),
+            requested: Vec::new(),
+            pending_scroll: 0,
+            align_top: false,
+            line_index: vec![0],
+            indexed_bytes: 0,
+            total_lines: None,
+            found: None,
+            cancel: Arc::new(AtomicBool::new(false)),
+        };
+
+        for (page, chunk) in initial.chunks(PAGE_SIZE as usize).enumerate() {
+            let page = page as u64;
+            // A short chunk is only a valid page when it is the end of the file
+            if chunk.len() as u64 == paged.page_len(page) {
+                paged.pages.insert(page, chunk.to_vec());
+            }
+        }
+        paged
+    }
+
+    fn page_len(&self, page: u64) -> u64 {
+        PAGE_SIZE.min(self.size.saturating_sub(page * PAGE_SIZE))
+    }
+
+    /// Pages that should be fetched; each page is only handed out once.
+    pub fn take_requests(&mut self) -> Vec<u64> {
+        std::mem::take(&mut self.requested)
+    }
+
+    pub fn insert_page(&mut self, page: u64, data: Vec<u8>) {
+        // A short read means the source changed size; don't cache a partial page
+        if (data.len() as u64) < self.page_len(page) {
+            self.page_failed(page);
+            return;
+        }
+        self.pending.remove(&page);
+        self.pages.insert(page, data);
+
+        // Evict the pages farthest from the current view
+        let top_page = self.top_offset / PAGE_SIZE;
+        while self.pages.len() > MAX_CACHED_PAGES {
+            let Some(farthest) = self
+                .pages
+                .keys()
+                .copied()
+                .max_by_key(|p| p.abs_diff(top_page))
+            else {
+                break;
+            };
+            self.pages.remove(&farthest);
+        }
+    }
+
+    /// Marks a page whose read failed so it is not requested again on every frame.
+    pub fn page_failed(&mut self, page: u64) {
+        self.pending.remove(&page);
+        self.failed.insert(page);
+    }
+
+    pub fn has_failed_pages(&self) -> bool {
+        !self.failed.is_empty()
+    }
+
+    /// Lets failed pages be requested again the next time they are shown.
+    pub fn retry_failed(&mut self) {
+        self.failed.clear();
+    }
+
+    /// Extends the view as a decompression spool fills. The old last page
+    /// was short, so it is dropped and fetched again, as are reads that
+    /// failed while racing the writer.
+    pub fn grow(&mut self, size: u64) {
+        if size <= self.size {
+            return;
+        }
+        if !self.size.is_multiple_of(PAGE_SIZE) {
+            self.pages.remove(&(self.size / PAGE_SIZE));
+        }
+        self.failed.clear();
+        self.size = size;
+    }
+
+    pub fn apply_index_update(&mut self, update: LineIndexUpdate) {
+        self.line_index.extend(update.checkpoints);
+        self.indexed_bytes = update.scanned_bytes;
+        self.total_lines = update.total_lines;
+    }
+
+    fn page(&mut self, page: u64) -> Option<&[u8]> {
+        if !self.pages.contains_key(&page) {
+            if !self.failed.contains(&page) && self.pending.insert(page) {
+                self.requested.push(page);
+            }
+            return None;
+        }
+        self.pages.get(&page).map(Vec::as_slice)
+    }
+
+    pub fn scroll(&mut self, rows: i64) {
+        self.pending_scroll += rows;
+    }
+
+    /// Moves the view to the start of the row containing `offset`.
+    pub fn jump_to_offset(&mut self, offset: u64) {
+        self.top_offset = offset.min(self.size);
+        self.pending_scroll = 0;
+        self.align_top = true;
+    }
+
+    /// Shows the last `rows` rows of the file.
+    pub fn jump_to_end(&mut self, rows: usize) {
+        self.jump_to_offset(self.size);
+        self.pending_scroll = -(rows.saturating_sub(1) as i64);
+    }
+
+    /// Jumps to a 1-based line number; returns false if the index has not reached it yet.
+    pub fn go_to_line(&mut self, line: u64) -> bool {
+        let line = line.saturating_sub(1);
+        let checkpoint = (line / LINE_INDEX_STRIDE) as usize;
+        let Some(&offset) = self.line_index.get(checkpoint) else {
+            return false;
+        };
+        self.top_offset = offset;
+        self.align_top = false;
+        self.pending_scroll = (line % LINE_INDEX_STRIDE) as i64;
+        true
+    }
+
+    /// End of the row starting at `start`: just past its '\n', or capped by
+    /// MAX_ROW_BYTES / end of file. None while a page is being fetched.
+    fn row_end(&mut self, start: u64) -> Option<u64> {
+        let limit = (start + MAX_ROW_BYTES).min(self.size);
+        let mut pos = start;
+        while pos < limit {
+            let page = pos / PAGE_SIZE;
+            let page_start = page * PAGE_SIZE;
+            let data = self.page(page)?;
+            let from = (pos - page_start) as usize;
+            let to = ((limit - page_start) as usize).min(data.len());
+            if from >= to {
+                return Some(limit);
+            }
+            if let Some(i) = data[from..to].iter().position(|&b| b == b'\n') {
+                return Some(pos + i as u64 + 1);
+            }
+            pos = page_start + to as u64;
+        }
+        Some(limit)
+    }
+
+    /// Start of the row containing the byte at `end - 1`.
+    fn row_start_before(&mut self, end: u64) -> Option<u64> {
+        if end == 0 {
+            return Some(0);
+        }
+        let floor = end.saturating_sub(MAX_ROW_BYTES);
+        // The byte at end - 1 may itself be the '\n' that terminates the row
+        let mut pos = end - 1;
+        while pos > floor {
+            let page = (pos - 1) / PAGE_SIZE;
+            let page_start = page * PAGE_SIZE;
+            let data = self.page(page)?;
+            let from = (floor.max(page_start) - page_start) as usize;
+            let to = ((pos - page_start) as usize).min(data.len());
+            if let Some(i) = data[from..to].iter().rposition(|&b| b == b'\n') {
+                return Some(page_start + i as u64 + 1);
+            }
+            pos = floor.max(page_start);
+        }
+        Some(floor)
+    }
+
+    fn copy_range(&mut self, start: u64, end: u64) -> Option<Vec<u8>> {
+        let mut bytes = Vec::with_capacity((end - start) as usize);
+        let mut pos = start;
+        while pos < end {
+            let page = pos / PAGE_SIZE;
+            let page_start = page * PAGE_SIZE;
+            let data = self.page(page)?;
+            let from = (pos - page_start) as usize;
+            let to = ((end - page_start) as usize).min(data.len());
+            if from >= to {
+                break;
+            }
+            bytes.extend_from_slice(&data[from..to]);
+            pos = page_start + to as u64;
+        }
+        Some(bytes)
+    }
+
+    /// 0-based number of the line containing `offset`, counted from the
+    /// nearest index checkpoint. None when unknown or too far to count.
+    fn line_number_at(&mut self, offset: u64) -> Option<u64> {
+        let idx = self
+            .line_index
+            .partition_point(|&o| o <= offset)
+            .checked_sub(1)?;
+        let checkpoint = self.line_index[idx];
+        if offset - checkpoint > MAX_LINE_COUNT_BYTES {
+            return None;
+        }
+        let bytes = self.copy_range(checkpoint, offset)?;
+        let newlines = bytes.iter().filter(|&&b| b == b'\n').count() as u64;
+        Some(idx as u64 * LINE_INDEX_STRIDE + newlines)
+    }
+
+    fn apply_pending(&mut self) {
+        if self.align_top {
+            let Some(start) = self.row_start_before((self.top_offset + 1).min(self.size)) else {
+                return;
+            };
+            self.top_offset = start;
+            self.align_top = false;
+        }
+        while self.pending_scroll > 0 {
+            let Some(end) = self.row_end(self.top_offset) else {
+                return;
+            };
+            if end >= self.size {
+                self.pending_scroll = 0;
+                break;
+            }
+            self.top_offset = end;
+            self.pending_scroll -= 1;
+        }
+        while self.pending_scroll < 0 {
+            if self.top_offset == 0 {
+                self.pending_scroll = 0;
+                break;
+            }
+            let Some(start) = self.row_start_before(self.top_offset) else {
+                return;
+            };
+            self.top_offset = start;
+            self.pending_scroll += 1;
+        }
+    }
+
+    /// Next match after `from` (or the last one before it) within the pages
+    /// that are currently loaded. Matches running into a missing page are not found.
+    pub fn find_loaded(
+        &self,
+        regex: &regex::bytes::Regex,
+        from: u64,
+        forward: bool,
+    ) -> Option<Range<u64>> {
+        let mut loaded: Vec<u64> = self.pages.keys().copied().collect();
+        loaded.sort_unstable();
+
+        let mut found = None;
+        let mut run_start = 0;
+        while run_start < loaded.len() {
+            // Search each run of consecutive pages as one buffer
+            let mut run_end = run_start + 1;
+            while run_end < loaded.len() && loaded[run_end] == loaded[run_end - 1] + 1 {
+                run_end += 1;
+            }
+            let base = loaded[run_start] * PAGE_SIZE;
+            let buffer: Vec<u8> = loaded[run_start..run_end]
+                .iter()
+                .flat_map(|page| self.pages[page].iter().copied())
+                .collect();
+            for m in regex.find_iter(&buffer).filter(|m| !m.is_empty()) {
+                let range = base + m.start() as u64..base + m.end() as u64;
+                if forward && range.start >= from {
+                    return Some(range);
+                }
+                if !forward && range.start < from {
+                    found = Some(range);
+                }
+            }
+            run_start = run_end;
+        }
+        found
+    }
+
+    /// Rows to display from the current position. Rows whose pages are still
+    /// being fetched are left out; the caller should fetch `take_requests()`.
+    pub fn visible_rows(&mut self, max_rows: usize) -> Vec<DisplayRow> {
+        self.apply_pending();
+
+        let mut rows = Vec::new();
+        let mut pos = self.top_offset;
+        let mut line = self.line_number_at(pos);
+        // The first row continues a long line unless it follows a '\n'
+        let mut line_start = pos == 0
+            || self
+                .copy_range(pos - 1, pos)
+                .is_some_and(|b| b.first() == Some(&b'\n'));
+
+        while rows.len() < max_rows && pos < self.size {
+            let Some(end) = self.row_end(pos) else {
+                break;
+            };
+            let Some(bytes) = self.copy_range(pos, end) else {
+                break;
+            };
+            let ends_line = bytes.last() == Some(&b'\n');
+            rows.push(DisplayRow {
+                offset: pos,
+                bytes,
+                line_number: if line_start {
+                    line.map(|l| l + 1)
+                } else {
+                    None
+                },
+            });
+            if ends_line {
+                line = line.map(|l| l + 1);
+            }
+            line_start = ends_line;
+            pos = end;
+        }
+        rows
+    }
+}
+
+/// Parses a byte offset written in decimal or as 0x-prefixed hex.
+pub fn parse_offset(text: &str) -> Option<u64> {
+    let text = text.trim();
+    match text.strip_prefix("0x").or_else(|| text.strip_prefix("0X")) {
+        Some(hex) => u64::from_str_radix(hex, 16).ok(),
+        None => text.parse().ok(),
+    }
+}
+
+/// Counts newlines in `chunk` (starting at file offset `base`) and records the
+/// start offset of every LINE_INDEX_STRIDE-th line in `checkpoints`.
+pub fn index_chunk(chunk: &[u8], base: u64, lines: &mut u64, checkpoints: &mut Vec<u64>) {
+    for (i, &b) in chunk.iter().enumerate() {
+        if b == b'\n' {
+            *lines += 1;
+            if lines.is_multiple_of(LINE_INDEX_STRIDE) {
+                checkpoints.push(base + i as u64 + 1);
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn paged_with(content: &[u8]) -> PagedFile {
+        PagedFile::new(content.len() as u64, content)
+    }
+
+    fn row_texts(rows: &[DisplayRow]) -> Vec<String> {
+        rows.iter()
+            .map(|r| String::from_utf8_lossy(&r.bytes).into_owned())
+            .collect()
+    }
+
+    #[test]
+    fn test_visible_rows_and_scroll() {
+        let mut paged = paged_with(b"one\ntwo\nthree\nfour");
+        let rows = paged.visible_rows(2);
+        assert_eq!(row_texts(&rows), vec!["one\n", "two\n"]);
+        assert_eq!(rows[0].line_number, Some(1));
+        assert_eq!(rows[1].line_number, Some(2));
+
+        paged.scroll(2);
+        let rows = paged.visible_rows(5);
+        assert_eq!(row_texts(&rows), vec!["three\n", "four"]);
+        assert_eq!(rows[0].line_number, Some(3));
+
+        paged.scroll(-1);
+        assert_eq!(row_texts(&paged.visible_rows(1)), vec!["two\n"]);
+    }
+
+    #[test]
+    fn test_jump_to_offset_aligns_to_line_start() {
+        let mut paged = paged_with(b"alpha\nbeta\ngamma\n");
+        paged.jump_to_offset(8);
+        assert_eq!(row_texts(&paged.visible_rows(1)), vec!["beta\n"]);
+
+        paged.jump_to_end(2);
+        assert_eq!(row_texts(&paged.visible_rows(2)), vec!["beta\n", "gamma\n"]);
+    }
+
+    #[test]
+    fn test_missing_pages_are_requested_once() {
+        let size = PAGE_SIZE * 3;
+        let mut paged = PagedFile::new(size, &[]);
+        assert!(paged.visible_rows(10).is_empty());
+        assert_eq!(paged.take_requests(), vec![0]);
+        paged.visible_rows(10);
+        assert!(paged.take_requests().is_empty());
+
+        paged.insert_page(0, vec![b'a'; PAGE_SIZE as usize]);
+        // The first row is capped at MAX_ROW_BYTES, so it fits in page 0
+        let rows = paged.visible_rows(1);
+        assert_eq!(rows[0].bytes.len() as u64, MAX_ROW_BYTES);
+    }
+
+    #[test]
+    fn test_failed_pages_wait_for_retry() {
+        let mut paged = PagedFile::new(PAGE_SIZE * 2, &[]);
+        paged.visible_rows(10);
+        assert_eq!(paged.take_requests(), vec![0]);
+
+        paged.page_failed(0);
+        assert!(paged.has_failed_pages());
+        paged.visible_rows(10);
+        assert!(paged.take_requests().is_empty());
+
+        paged.retry_failed();
+        paged.visible_rows(10);
+        assert_eq!(paged.take_requests(), vec![0]);
+    }
+
+    #[test]
+    fn test_grow_refetches_short_last_page() {
+        let mut paged = paged_with(b"one\ntw");
+        paged.grow(PAGE_SIZE * 2);
+        paged.visible_rows(1);
+        assert_eq!(paged.take_requests(), vec![0]);
+
+        // A read that raced the writer is not cached
+        paged.insert_page(0, b"one\ntwo\n".to_vec());
+        assert!(paged.has_failed_pages());
+        paged.visible_rows(1);
+        assert!(paged.take_requests().is_empty());
+        paged.grow(PAGE_SIZE * 3);
+        paged.visible_rows(1);
+        assert_eq!(paged.take_requests(), vec![0]);
+    }
+
+    #[test]
+    fn test_find_loaded_spans_pages() {
+        let mut content = vec![b'.'; (PAGE_SIZE * 3) as usize];
+        let across = PAGE_SIZE as usize - 2;
+        let last = content.len() - 10;
+        for at in [10, across, last] {
+            content[at..at + 5].copy_from_slice(b"ERROR");
+        }
+        let loaded = (PAGE_SIZE * 2) as usize;
+        let mut paged = PagedFile::new(content.len() as u64, &content[..loaded]);
+        let re = regex::bytes::Regex::new("ERROR").unwrap();
+        let range = |at: usize| at as u64..at as u64 + 5;
+
+        assert_eq!(paged.find_loaded(&re, 0, true), Some(range(10)));
+        assert_eq!(paged.find_loaded(&re, 11, true), Some(range(across)));
+        assert_eq!(
+            paged.find_loaded(&re, across as u64, false),
+            Some(range(10))
+        );
+        // Page 2 is not loaded yet
+        assert_eq!(paged.find_loaded(&re, across as u64 + 1, true), None);
+
+        paged.insert_page(2, content[loaded..].to_vec());
+        assert_eq!(
+            paged.find_loaded(&re, across as u64 + 1, true),
+            Some(range(last))
+        );
+    }
+
+    #[test]
+    fn test_parse_offset() {
+        assert_eq!(parse_offset("1024"), Some(1024));
+        assert_eq!(parse_offset(" 0x1F "), Some(31));
+        assert_eq!(parse_offset("0xZZ"), None);
+        assert_eq!(parse_offset(""), None);
+    }
+
+    #[test]
+    fn test_index_chunk_and_go_to_line() {
+        let content: Vec<u8> = (0..2500)
+            .flat_map(|i| format!("{}\n", i).into_bytes())
+            .collect();
+        let mut lines = 0;
+        let mut checkpoints = Vec::new();
+        let (first, second) = content.split_at(content.len() / 2);
+        index_chunk(first, 0, &mut lines, &mut checkpoints);
+        index_chunk(second, first.len() as u64, &mut lines, &mut checkpoints);
+        assert_eq!(lines, 2500);
+        assert_eq!(checkpoints.len(), 2);
+
+        let mut paged = paged_with(&content);
+        assert!(!paged.go_to_line(1500));
+        paged.apply_index_update(LineIndexUpdate {
+            checkpoints,
+            scanned_bytes: content.len() as u64,
+            total_lines: Some(lines),
+        });
+        assert!(paged.go_to_line(1500));
+        let rows = paged.visible_rows(1);
+        assert_eq!(row_texts(&rows), vec!["1499\n"]);
+        assert_eq!(rows[0].line_number, Some(1500));
+    }
+}
@@ -0,0 +1,146 @@
+/// Suggestions listed under the path field.
+pub const MAX_SUGGESTIONS: usize = 12;
+
+/// Clickable segments of an absolute path as (label, path up to it).
+pub fn breadcrumbs(path: &str) -> Vec<(String, String)> {
+    let mut crumbs = vec![("/".to_string(), "/".to_string())];
+    let mut prefix = String::new();
+    for segment in path.split('/').filter(|s| !s.is_empty()) {
+        prefix.push('/');
+        prefix.push_str(segment);
+        crumbs.push((segment.to_string(), prefix.clone()));
+    }
+    crumbs
+}
+
+/// Splits typed text into the directory to list and the name being typed:
+/// "/var/lo" is ("/var", "lo"). None for text that is not an absolute path.
+pub fn split_input(input: &str) -> Option<(String, &str)> {
+    let slash = input.rfind('/')?;
+    if !input.starts_with('/') {
+        return None;
+    }
+    let dir = match &input[..slash] {
+        "" => "/".to_string(),
+        dir => dir.to_string(),
+    };
+    Some((dir, &input[slash + 1..]))
+}
+
+fn join(dir: &str, name: &str) -> String {
+    if dir.ends_with('/') {
+        format!("{}{}", dir, name)
+    } else {
+        format!("{}/{}", dir, name)
+    }
+}
+
+/// Directory names of one parent, fetched with readdir for completing the
+/// path being typed.
+#[derive(Debug, Default)]
+pub struct PathCompletion {
+    /// Directory the names belong to (or are being fetched for)
+    pub dir: Option<String>,
+    pub names: Vec<String>,
+}
+
+impl PathCompletion {
+    /// True if `dir` still has to be fetched; marks it as requested.
+    pub fn request(&mut self, dir: &str) -> bool {
+        if self.dir.as_deref() == Some(dir) {
+            return false;
+        }
+        self.dir = Some(dir.to_string());
+        self.names.clear();
+        true
+    }
+
+    pub fn set(&mut self, dir: &str, mut names: Vec<String>) {
+        if self.dir.as_deref() == Some(dir) {
+            names.sort();
+            self.names = names;
+        }
+    }
+
+    /// Names completing `prefix`. Hidden directories only show once a dot is typed.
+    pub fn matches<'a>(&'a self, prefix: &'a str) -> impl Iterator<Item = &'a String> + 'a {
+        self.names.iter().filter(move |n| {
+            n.starts_with(prefix) && (prefix.starts_with('.') || !n.starts_with('.'))
+        })
+    }
+
+    /// Full path for a picked suggestion.
+    pub fn pick(&self, name: &str) -> Option<String> {
+        Some(join(self.dir.as_deref()?, name) + "/")
+    }
+
+    /// Shell-style Tab completion: a single match is completed with a
+    /// trailing slash, several are extended to their common prefix. None
+    /// if nothing would change.
+    pub fn complete(&self, input: &str) -> Option<String> {
+        let (dir, prefix) = split_input(input)?;
+        if self.dir.as_deref() != Some(dir.as_str()) {
+            return None;
+        }
+        let mut matches = self.matches(prefix);
+        let first = matches.next()?;
+        let mut common = first.as_str();
+        let mut single = true;
+        for name in matches {
+            single = false;
+            let len = common
+                .char_indices()
+                .zip(name.chars())
+                .find(|((_, a), b)| a != b)
+                .map_or(common.len().min(name.len()), |((i, _), _)| i);
+            common = &common[..len];
+        }
+        if single {
+            return self.pick(first);
+        }
+        (common.len() > prefix.len()).then(|| join(&dir, common))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_breadcrumbs() {
+        assert_eq!(breadcrumbs("/"), [("/".to_string(), "/".to_string())]);
+        let crumbs = breadcrumbs("/var/log/");
+        let paths: Vec<_> = crumbs.iter().map(|(_, p)| p.as_str()).collect();
+        assert_eq!(paths, ["/", "/var", "/var/log"]);
+        assert_eq!(crumbs[2].0, "log");
+    }
+
+    #[test]
+    fn test_split_input() {
+        assert_eq!(split_input("/var/lo"), Some(("/var".to_string(), "lo")));
+        assert_eq!(split_input("/var/"), Some(("/var".to_string(), "")));
+        assert_eq!(split_input("/v"), Some(("/".to_string(), "v")));
+        assert_eq!(split_input("var"), None);
+    }
+
+    #[test]
+    fn test_tab_completion() {
+        let mut completion = PathCompletion::default();
+        assert!(completion.request("/var"));
+        assert!(!completion.request("/var"));
+        let names = ["log", "lib", "local", "locale", "lock", ".cache", "tmp"];
+        completion.set("/var", names.map(String::from).to_vec());
+
+        assert_eq!(completion.complete("/var/t").as_deref(), Some("/var/tmp/"));
+        assert_eq!(completion.complete("/var/loca").as_deref(), Some("/var/local"));
+        // Ambiguous with nothing more in common
+        assert_eq!(completion.complete("/var/l"), None);
+        assert_eq!(
+            completion.complete("/var/.").as_deref(),
+            Some("/var/.cache/")
+        );
+        assert_eq!(completion.matches("").count(), 6);
+        // Names of another directory are not used
+        assert_eq!(completion.complete("/etc/t"), None);
+    }
+}
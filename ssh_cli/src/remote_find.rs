@@ -0,0 +1,468 @@
+use ssh2::{Channel, ExtendedData, FileStat, Session, Sftp};
+use std::io::{self, Read};
+use std::path::Path;
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::{Arc, Mutex, mpsc};
+use std::thread;
+use std::time::Duration;
+
+use crate::app::AppMessage;
+use crate::model::SymlinkInfo;
+use crate::search_filter::CompiledFilter;
+use crate::ssh::entry_from_stat;
+use crate::walk::{MAX_WALK_ENTRIES, WalkLimit};
+
+/// Printed by a first `find` run. Output without it means the account has no
+/// usable shell or the server's `find` lacks `-printf`.
+const MARKER: &[u8] = b"ssh_cli-find";
+/// Type, link target type, mode, size, uid, gid, atime, mtime, link target, path
+const FORMAT: &str = r"%y\0%Y\0%m\0%s\0%U\0%G\0%A@\0%T@\0%l\0%p\0";
+const FIELDS: usize = 10;
+/// Matches sent to the list at a time.
+const BATCH_SIZE: usize = 500;
+/// Wait between reads while `find` has no new output.
+const POLL_INTERVAL: Duration = Duration::from_millis(20);
+
+/// Quotes one argument for a POSIX shell.
+pub fn shell_quote(arg: &str) -> String {
+    format!("'{}'", arg.replace('\'', r"'\''"))
+}
+
+/// The shell command: a marker from `find` itself, then one NUL-separated
+/// record per entry below `base_path`.
+pub fn find_command(base_path: &str, filter: &CompiledFilter) -> String {
+    let base = shell_quote(base_path);
+    let mut command = format!(
+        "find {} -maxdepth 0 -printf {} && find {} -mindepth 1",
+        base,
+        shell_quote(&format!("{}\\0", String::from_utf8_lossy(MARKER))),
+        base
+    );
+    for arg in filter.find_args() {
+        command.push(' ');
+        command.push_str(&shell_quote(&arg));
+    }
+    command.push_str(" -printf ");
+    command.push_str(&shell_quote(FORMAT));
+    command
+}
+
+/// Runs the search as `find` on an exec channel, streaming matches like
+/// `search_files_streaming`. Returns Ok(false) without sending anything when
+/// the server cannot run it, so the caller can walk the tree over SFTP instead.
+/// Following links is left to the SFTP walker: under `find -L` links no
+/// longer show as links.
+pub fn find_files_streaming(
+    session_arc: &Arc<Mutex<Session>>,
+    sftp_arc: &Arc<Mutex<Sftp>>,
+    base_path: &str,
+    filter: &CompiledFilter,
+    id: u64,
+    cancel: &AtomicBool,
+    tx: &mpsc::Sender<AppMessage>,
+) -> anyhow::Result<bool> {
+    if filter.walk.follow_links {
+        return Ok(false);
+    }
+    let mut channel = {
+        let session = session_arc
+            .lock()
+            .map_err(|_| anyhow::anyhow!("Lock error"))?;
+        let Ok(mut channel) = session.channel_session() else {
+            return Ok(false);
+        };
+        // Unread stderr would stall the channel once its window fills
+        channel.handle_extended_data(ExtendedData::Ignore)?;
+        if channel.exec(&find_command(base_path, filter)).is_err() {
+            return Ok(false);
+        }
+        channel
+    };
+    // SFTP-only accounts may start a server reading stdin instead; EOF ends it
+    channel.send_eof()?;
+
+    let mut parser = RecordParser::default();
+    let mut buffer = vec![0u8; 64 * 1024];
+    let mut verified = false;
+    let mut batch = Vec::new();
+    let mut scanned = 1;
+    let mut found = 0;
+    let mut entries = 0;
+    let mut limit = None;
+    loop {
+        if cancel.load(Ordering::SeqCst) {
+            // Results after stopping are not sent, as with the SFTP walker
+            let _ = channel.close();
+            let _ = tx.send(AppMessage::SearchFinished(id, scanned, true, None));
+            return Ok(true);
+        }
+        let Some(n) = read_available(session_arc, sftp_arc, &mut channel, &mut buffer)? else {
+            thread::sleep(POLL_INTERVAL);
+            continue;
+        };
+        if !verified {
+            match parser.take_marker(&buffer[..n]) {
+                Some(true) => {
+                    verified = true;
+                    let _ = tx.send(AppMessage::SearchStarted(id));
+                }
+                Some(false) => return Ok(false),
+                None if n == 0 => return Ok(false),
+                None => continue,
+            }
+        } else {
+            parser.feed(&buffer[..n]);
+        }
+
+        for record in parser.records.drain(..) {
+            entries += 1;
+            if entries > MAX_WALK_ENTRIES {
+                limit = Some(WalkLimit::Entries);
+                break;
+            }
+            if record.stat.is_dir() {
+                scanned += 1;
+            }
+            if filter.matches(&record.name, &record.stat) {
+                batch.push(entry_from_stat(
+                    Path::new(&record.path),
+                    record.stat,
+                    record.name,
+                    record.link,
+                ));
+            }
+        }
+        let done = n == 0 || limit.is_some();
+        if batch.len() >= BATCH_SIZE || (done && !batch.is_empty()) {
+            found += batch.len();
+            let _ = tx.send(AppMessage::SearchBatch(id, std::mem::take(&mut batch)));
+        }
+        let _ = tx.send(AppMessage::SearchProgress(id, scanned, found));
+        if done {
+            break;
+        }
+    }
+    if limit.is_some() {
+        let _ = channel.close();
+    }
+    let _ = channel.wait_close();
+
+    let _ = tx.send(AppMessage::SearchFinished(id, scanned, false, limit));
+    Ok(true)
+}
+
+/// Reads whatever output has arrived without waiting for more, so a quiet
+/// `find` neither stalls other workers nor delays cancellation. None when
+/// nothing is available yet. Blocking mode is session-wide, so SFTP is
+/// locked out while it is off.
+fn read_available(
+    session_arc: &Arc<Mutex<Session>>,
+    sftp_arc: &Arc<Mutex<Sftp>>,
+    channel: &mut Channel,
+    buffer: &mut [u8],
+) -> anyhow::Result<Option<usize>> {
+    let session = session_arc
+        .lock()
+        .map_err(|_| anyhow::anyhow!("Lock error"))?;
+    let _sftp = sftp_arc.lock().map_err(|_| anyhow::anyhow!("Lock error"))?;
+    session.set_blocking(false);
+    let result = channel.read(buffer);
+    session.set_blocking(true);
+    match result {
+        Ok(n) => Ok(Some(n)),
+        Err(e) if e.kind() == io::ErrorKind::WouldBlock => Ok(None),
+        Err(e) => Err(e.into()),
+    }
+}
+
+/// One entry printed by `find`.
+struct FoundEntry {
+    name: String,
+    path: String,
+    stat: FileStat,
+    link: Option<SymlinkInfo>,
+}
+
+/// Splits `find` output into records as it arrives.
+#[derive(Default)]
+struct RecordParser {
+    pending: Vec<u8>,
+    fields: Vec<Vec<u8>>,
+    records: Vec<FoundEntry>,
+}
+
+impl RecordParser {
+    /// Consumes the marker. None until enough output has arrived to tell.
+    fn take_marker(&mut self, chunk: &[u8]) -> Option<bool> {
+        self.pending.extend_from_slice(chunk);
+        match self.pending.iter().position(|&b| b == 0) {
+            Some(end) if self.pending[..end] == *MARKER => {
+                let rest = self.pending.split_off(end + 1);
+                self.pending.clear();
+                self.feed(&rest);
+                Some(true)
+            }
+            Some(_) => Some(false),
+            None if self.pending.len() > MARKER.len() => Some(false),
+            None => None,
+        }
+    }
+
+    fn feed(&mut self, chunk: &[u8]) {
+        self.pending.extend_from_slice(chunk);
+        let mut start = 0;
+        while let Some(len) = self.pending[start..].iter().position(|&b| b == 0) {
+            self.fields.push(self.pending[start..start + len].to_vec());
+            start += len + 1;
+            if self.fields.len() == FIELDS {
+                // Records that do not parse are skipped
+                if let Some(record) = parse_record(&self.fields) {
+                    self.records.push(record);
+                }
+                self.fields.clear();
+            }
+        }
+        self.pending.drain(..start);
+    }
+}
+
+fn parse_record(fields: &[Vec<u8>]) -> Option<FoundEntry> {
+    let text = |i: usize| String::from_utf8_lossy(&fields[i]).into_owned();
+    let number = |i: usize| text(i).parse::<u64>().ok();
+    // "%T@" prints fractional seconds
+    let seconds = |i: usize| {
+        text(i)
+            .split('.')
+            .next()
+            .and_then(|s| s.parse::<u64>().ok())
+    };
+
+    let kind = *fields[0].first()?;
+    let type_bits = match kind {
+        b'f' => 0o100000,
+        b'd' => 0o040000,
+        b'l' => 0o120000,
+        b'p' => 0o010000,
+        b's' => 0o140000,
+        b'c' => 0o020000,
+        b'b' => 0o060000,
+        _ => 0,
+    };
+    let permissions = u32::from_str_radix(&text(2), 8).ok()?;
+    let path = text(9);
+    let name = Path::new(&path).file_name()?.to_string_lossy().into_owned();
+    let link = (kind == b'l').then(|| SymlinkInfo {
+        target: text(8),
+        target_is_dir: fields[1] == b"d",
+        // "N" for a missing target, "L" for a loop
+        broken: matches!(fields[1].as_slice(), b"N" | b"L" | b"?"),
+    });
+    Some(FoundEntry {
+        name,
+        path,
+        stat: FileStat {
+            size: number(3),
+            uid: number(4).map(|n| n as u32),
+            gid: number(5).map(|n| n as u32),
+            perm: Some(type_bits | permissions),
+            atime: seconds(6),
+            mtime: seconds(7),
+        },
+        link,
+    })
+}
+
+#[cfg(test)]
+pub(crate) mod tests {
+    use super::*;
+    use crate::search_filter::SearchFilter;
+
+    #[test]
+    fn test_shell_quote() {
+        assert_eq!(shell_quote("/srv/data"), "'/srv/data'");
+        assert_eq!(shell_quote("it's $HOME"), r"'it'\''s $HOME'");
+    }
+
+    #[test]
+    fn test_find_command() {
+        let filter = SearchFilter {
+            pattern: "*.log".to_string(),
+            recursive: true,
+            ..Default::default()
+        }
+        .compile()
+        .unwrap();
+        assert_eq!(
+            find_command("/var/log's", &filter),
+            r"find '/var/log'\''s' -maxdepth 0 -printf 'ssh_cli-find\0' && find '/var/log'\''s' -mindepth 1 '-maxdepth' '64' '-name' '*.log' -printf '%y\0%Y\0%m\0%s\0%U\0%G\0%A@\0%T@\0%l\0%p\0'"
+        );
+    }
+
+    #[test]
+    fn test_record_parser() {
+        let output: &[u8] = b"ssh_cli-find\x00\
+            f\x00f\x00644\x001024\x001000\x001000\x001700000000.25\x001700000001.5\x00\x00\
+            /srv/a b.log\x00\
+            l\x00N\x00777\x007\x000\x000\x001\x002\x00gone\x00/srv/link\x00";
+        let mut parser = RecordParser::default();
+        // Arrives split mid-marker and mid-record
+        assert_eq!(parser.take_marker(&output[..5]), None);
+        assert_eq!(parser.take_marker(&output[5..30]), Some(true));
+        parser.feed(&output[30..]);
+
+        assert_eq!(parser.records.len(), 2);
+        let file = &parser.records[0];
+        assert_eq!(file.name, "a b.log");
+        assert_eq!(file.stat.perm, Some(0o100644));
+        assert_eq!(file.stat.size, Some(1024));
+        assert_eq!(file.stat.mtime, Some(1700000001));
+        let link = parser.records[1].link.as_ref().unwrap();
+        assert_eq!(link.target, "gone");
+        assert!(link.broken);
+
+        let mut other = RecordParser::default();
+        assert_eq!(
+            other.take_marker(b"This account is currently not available.\n"),
+            Some(false)
+        );
+    }
+
+    /// Server for the ignored tests below, from SSH_CLI_TEST_HOST (host:port),
+    /// SSH_CLI_TEST_USER and SSH_CLI_TEST_PASS.
+    pub(crate) fn test_server() -> (Arc<Mutex<Session>>, Arc<Mutex<ssh2::Sftp>>, String) {
+        let var = |name: &str| std::env::var(name).unwrap_or_else(|_| panic!("{} not set", name));
+        let (session, sftp, home) = crate::ssh::connect_session(
+            &var("SSH_CLI_TEST_HOST"),
+            &var("SSH_CLI_TEST_USER"),
+            &var("SSH_CLI_TEST_PASS"),
+        )
+        .unwrap();
+        (
+            Arc::new(Mutex::new(session)),
+            Arc::new(Mutex::new(sftp)),
+            home,
+        )
+    }
+
+    /// Creates a small tree under a fresh directory in the home directory.
+    fn make_tree(sftp: &ssh2::Sftp, home: &str) -> String {
+        let base = format!(
+            "{}/ssh_cli_test_{}_{}",
+            home,
+            std::process::id(),
+            chrono::Utc::now().timestamp_millis()
+        );
+        for dir in ["", "/sub", "/sub/deep", "/node_modules", "/it's here"] {
+            sftp.mkdir(Path::new(&format!("{}{}", base, dir)), 0o755)
+                .unwrap();
+        }
+        for file in [
+            "/a.log",
+            "/b.txt",
+            "/sub/c.log",
+            "/sub/deep/d.log",
+            "/node_modules/e.log",
+            "/it's here/f.log",
+        ] {
+            sftp.create(Path::new(&format!("{}{}", base, file)))
+                .unwrap();
+        }
+        sftp.symlink(Path::new("a.log"), Path::new(&format!("{}/link.log", base)))
+            .unwrap();
+        base
+    }
+
+    fn remove_tree(sftp: &ssh2::Sftp, path: &Path) {
+        for (entry, stat) in sftp.readdir(path).unwrap_or_default() {
+            if stat.is_dir() {
+                remove_tree(sftp, &entry);
+            } else {
+                let _ = sftp.unlink(&entry);
+            }
+        }
+        let _ = sftp.rmdir(path);
+    }
+
+    fn found_paths(rx: &mpsc::Receiver<AppMessage>) -> Vec<String> {
+        let mut paths = Vec::new();
+        for message in rx.try_iter() {
+            if let AppMessage::SearchBatch(_, batch) = message {
+                paths.extend(batch.into_iter().map(|e| e.path));
+            }
+        }
+        paths.sort();
+        paths
+    }
+
+    #[test]
+    #[ignore = "needs a local sshd; see README"]
+    fn test_find_and_sftp_walker_agree() {
+        let (session, sftp, home) = test_server();
+        let base = make_tree(&sftp.lock().unwrap(), &home);
+        let filter = SearchFilter {
+            pattern: "*.log".to_string(),
+            recursive: true,
+            max_depth: "2".to_string(),
+            exclude: "node_modules".to_string(),
+            ..Default::default()
+        }
+        .compile()
+        .unwrap();
+        let cancel = AtomicBool::new(false);
+
+        let (tx, rx) = mpsc::channel();
+        let ran = find_files_streaming(&session, &sftp, &base, &filter, 1, &cancel, &tx);
+        let via_find = found_paths(&rx);
+        crate::ssh::search_files_streaming(&sftp, &base, &filter, 1, &cancel, tx.clone()).unwrap();
+        let via_sftp = found_paths(&rx);
+        remove_tree(&sftp.lock().unwrap(), Path::new(&base));
+
+        assert!(ran.unwrap(), "server-side find unavailable");
+        let expected: Vec<String> = ["/a.log", "/it's here/f.log", "/link.log", "/sub/c.log"]
+            .iter()
+            .map(|p| format!("{}{}", base, p))
+            .collect();
+        assert_eq!(via_find, expected);
+        assert_eq!(via_sftp, expected);
+    }
+
+    #[test]
+    #[ignore = "needs a local sshd; see README"]
+    fn test_find_entries_match_sftp_attributes() {
+        let (session, sftp, home) = test_server();
+        let base = make_tree(&sftp.lock().unwrap(), &home);
+        let filter = SearchFilter {
+            pattern: "link.log".to_string(),
+            ..Default::default()
+        }
+        .compile()
+        .unwrap();
+        let cancel = AtomicBool::new(false);
+
+        let (tx, rx) = mpsc::channel();
+        find_files_streaming(&session, &sftp, &base, &filter, 1, &cancel, &tx).unwrap();
+        crate::ssh::search_files_streaming(&sftp, &base, &filter, 1, &cancel, tx.clone()).unwrap();
+        let entries: Vec<_> = rx
+            .try_iter()
+            .filter_map(|m| match m {
+                AppMessage::SearchBatch(_, batch) => Some(batch),
+                _ => None,
+            })
+            .flatten()
+            .collect();
+        remove_tree(&sftp.lock().unwrap(), Path::new(&base));
+
+        let [via_find, via_sftp] = entries.as_slice() else {
+            panic!("expected one entry from each walker, got {}", entries.len());
+        };
+        assert_eq!(via_find.perm, via_sftp.perm);
+        assert_eq!(via_find.mtime, via_sftp.mtime);
+        assert_eq!(via_find.uid, via_sftp.uid);
+        let (find_link, sftp_link) = (via_find.link.as_ref(), via_sftp.link.as_ref());
+        assert_eq!(find_link.map(|l| &l.target), sftp_link.map(|l| &l.target));
+        assert_eq!(
+            find_link.map(|l| l.target_is_dir),
+            sftp_link.map(|l| l.target_is_dir)
+        );
+    }
+}
@@ -0,0 +1,403 @@
+use chrono::{Local, NaiveDate, NaiveDateTime, TimeZone};
+use regex::{Regex, RegexBuilder};
+use serde::{Deserialize, Serialize};
+use ssh2::FileStat;
+
+use crate::walk::{MAX_WALK_DEPTH, WalkOptions};
+
+/// Kind of entry a search is limited to. Links are matched as links, not by
+/// what they point to.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default, Serialize, Deserialize)]
+pub enum EntryType {
+    #[default]
+    Any,
+    File,
+    Dir,
+    Link,
+}
+
+impl EntryType {
+    pub const ALL: [EntryType; 4] = [
+        EntryType::Any,
+        EntryType::File,
+        EntryType::Dir,
+        EntryType::Link,
+    ];
+}
+
+impl std::fmt::Display for EntryType {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            EntryType::Any => write!(f, "Any type"),
+            EntryType::File => write!(f, "Files"),
+            EntryType::Dir => write!(f, "Directories"),
+            EntryType::Link => write!(f, "Links"),
+        }
+    }
+}
+
+/// Search bar input. Text fields are kept as typed and parsed by `compile`.
+#[derive(Debug, Clone, Default, PartialEq, Serialize, Deserialize)]
+#[serde(default)]
+pub struct SearchFilter {
+    /// Glob (`*.log`), or a regex when `regex` is set; empty matches everything
+    pub pattern: String,
+    pub regex: bool,
+    pub ignore_case: bool,
+    pub recursive: bool,
+    /// Sizes such as "512", "10K" or "1.5G"
+    pub min_size: String,
+    pub max_size: String,
+    /// Dates as "YYYY-MM-DD" or "YYYY-MM-DD HH:MM", local time
+    pub modified_after: String,
+    pub modified_before: String,
+    pub entry_type: EntryType,
+    /// Levels below the search directory to descend; empty for no limit
+    pub max_depth: String,
+    /// Comma-separated globs; matching entries are skipped and not descended into
+    pub exclude: String,
+    /// Enter symlinked directories (loops are detected)
+    pub follow_links: bool,
+    pub one_filesystem: bool,
+}
+
+impl SearchFilter {
+    /// True if nothing narrows the search, which would list the whole tree.
+    pub fn is_unrestricted(&self) -> bool {
+        let blank = |s: &String| s.trim().is_empty();
+        blank(&self.pattern)
+            && blank(&self.min_size)
+            && blank(&self.max_size)
+            && blank(&self.modified_after)
+            && blank(&self.modified_before)
+            && self.entry_type == EntryType::Any
+    }
+
+    pub fn compile(&self) -> Result<CompiledFilter, String> {
+        let pattern = self.pattern.trim();
+        let name = if self.regex {
+            build_regex(pattern, self.ignore_case)?
+        } else {
+            glob_regex(
+                if pattern.is_empty() { "*" } else { pattern },
+                self.ignore_case,
+            )?
+        };
+        let exclude_globs: Vec<String> = self
+            .exclude
+            .split(',')
+            .map(str::trim)
+            .filter(|glob| !glob.is_empty())
+            .map(str::to_string)
+            .collect();
+        let excludes = exclude_globs
+            .iter()
+            .map(|glob| glob_regex(glob, self.ignore_case))
+            .collect::<Result<_, _>>()?;
+        let max_depth = match self.max_depth.trim() {
+            _ if !self.recursive => Some(1),
+            "" => None,
+            depth => Some(
+                depth
+                    .parse::<usize>()
+                    .ok()
+                    .filter(|&d| d > 0)
+                    .ok_or_else(|| format!("Invalid max depth: {}", depth))?,
+            ),
+        };
+        Ok(CompiledFilter {
+            name,
+            name_glob: (!self.regex && !pattern.is_empty()).then(|| pattern.to_string()),
+            ignore_case: self.ignore_case,
+            excludes,
+            exclude_globs,
+            min_size: parse_optional(&self.min_size, parse_size, "size")?,
+            max_size: parse_optional(&self.max_size, parse_size, "size")?,
+            modified_after: parse_optional(&self.modified_after, parse_date, "date")?,
+            modified_before: parse_optional(&self.modified_before, parse_date, "date")?,
+            entry_type: self.entry_type,
+            max_depth,
+            walk: WalkOptions {
+                follow_links: self.follow_links,
+                one_filesystem: self.one_filesystem,
+            },
+        })
+    }
+}
+
+/// A validated filter, evaluated against the attributes `readdir` returns.
+#[derive(Debug, Clone)]
+pub struct CompiledFilter {
+    name: Regex,
+    /// The pattern when it is a glob, for narrowing server-side `find`
+    name_glob: Option<String>,
+    ignore_case: bool,
+    excludes: Vec<Regex>,
+    exclude_globs: Vec<String>,
+    min_size: Option<u64>,
+    max_size: Option<u64>,
+    modified_after: Option<i64>,
+    modified_before: Option<i64>,
+    entry_type: EntryType,
+    /// Depth of the deepest reported entries (1 = the search directory itself)
+    max_depth: Option<usize>,
+    pub walk: WalkOptions,
+}
+
+impl CompiledFilter {
+    /// Excluded entries are neither reported nor descended into.
+    pub fn is_excluded(&self, name: &str) -> bool {
+        self.excludes.iter().any(|re| re.is_match(name))
+    }
+
+    /// Whether the children of a directory at `depth` are still in range.
+    pub fn descends_into(&self, depth: usize) -> bool {
+        self.max_depth.is_none_or(|max| depth < max)
+    }
+
+    pub fn matches(&self, name: &str, stat: &FileStat) -> bool {
+        let file_type = stat.file_type();
+        let type_ok = match self.entry_type {
+            EntryType::Any => true,
+            EntryType::File => file_type.is_file(),
+            EntryType::Dir => file_type.is_dir(),
+            EntryType::Link => file_type.is_symlink(),
+        };
+        // Directory sizes say nothing about their contents, so a size limit skips them
+        let sized = self.min_size.is_some() || self.max_size.is_some();
+        let size_ok = !(sized && file_type.is_dir())
+            && stat.size.is_none_or(|size| {
+                self.min_size.is_none_or(|min| size >= min)
+                    && self.max_size.is_none_or(|max| size <= max)
+            });
+        let mtime = stat.mtime.map(|m| m as i64);
+        let date_ok = self
+            .modified_after
+            .is_none_or(|after| mtime.is_some_and(|m| m >= after))
+            && self
+                .modified_before
+                .is_none_or(|before| mtime.is_some_and(|m| m < before));
+        type_ok && size_ok && date_ok && self.name.is_match(name)
+    }
+
+    /// `find` tests that prune excluded names and the depth limit and drop
+    /// entries that cannot match. Only exact equivalents are used, so the
+    /// output is a superset that `matches` still has to check.
+    pub fn find_args(&self) -> Vec<String> {
+        let name_test = if self.ignore_case { "-iname" } else { "-name" };
+        let mut args = Vec::new();
+        if self.walk.one_filesystem {
+            args.push("-xdev".to_string());
+        }
+        let depth = self
+            .max_depth
+            .map_or(MAX_WALK_DEPTH, |d| d.min(MAX_WALK_DEPTH));
+        args.extend(["-maxdepth".to_string(), depth.to_string()]);
+        if !self.exclude_globs.is_empty() {
+            args.push("(".to_string());
+            for (i, glob) in self.exclude_globs.iter().enumerate() {
+                if i > 0 {
+                    args.push("-o".to_string());
+                }
+                args.extend([name_test.to_string(), fnmatch_escape(glob)]);
+            }
+            args.extend([")", "-prune", "-o"].map(str::to_string));
+        }
+        let type_letter = match self.entry_type {
+            EntryType::Any => None,
+            EntryType::File => Some("f"),
+            EntryType::Dir => Some("d"),
+            EntryType::Link => Some("l"),
+        };
+        if let Some(letter) = type_letter {
+            args.extend(["-type".to_string(), letter.to_string()]);
+        }
+        if let Some(glob) = &self.name_glob {
+            args.extend([name_test.to_string(), fnmatch_escape(glob)]);
+        }
+        args
+    }
+}
+
+/// Our globs only know `*` and `?`; fnmatch would also read `[` and `\`.
+fn fnmatch_escape(glob: &str) -> String {
+    let mut escaped = String::with_capacity(glob.len());
+    for c in glob.chars() {
+        if matches!(c, '[' | ']' | '\\') {
+            escaped.push('\\');
+        }
+        escaped.push(c);
+    }
+    escaped
+}
+
+fn build_regex(pattern: &str, ignore_case: bool) -> Result<Regex, String> {
+    RegexBuilder::new(pattern)
+        .case_insensitive(ignore_case)
+        .build()
+        .map_err(|e| e.to_string())
+}
+
+/// Whole-name glob with `*` and `?`.
+fn glob_regex(glob: &str, ignore_case: bool) -> Result<Regex, String> {
+    let pattern = regex::escape(glob).replace(r"\*", ".*").replace(r"\?", ".");
+    build_regex(&format!("^{}$", pattern), ignore_case)
+}
+
+fn parse_optional<T>(
+    text: &str,
+    parse: fn(&str) -> Option<T>,
+    what: &str,
+) -> Result<Option<T>, String> {
+    match text.trim() {
+        "" => Ok(None),
+        value => parse(value)
+            .map(Some)
+            .ok_or_else(|| format!("Invalid {}: {}", what, value)),
+    }
+}
+
+/// "1500", "10K", "1.5M", "2GiB"; suffixes are binary multiples.
+pub fn parse_size(text: &str) -> Option<u64> {
+    let text = text.trim().to_ascii_uppercase();
+    let text = text
+        .strip_suffix("IB")
+        .or_else(|| text.strip_suffix('B'))
+        .unwrap_or(&text);
+    let (number, unit) = match text.char_indices().last()? {
+        (i, c) if c.is_ascii_alphabetic() => (&text[..i], c),
+        _ => (text, ' '),
+    };
+    let multiplier: u64 = match unit {
+        ' ' => 1,
+        'K' => 1 << 10,
+        'M' => 1 << 20,
+        'G' => 1 << 30,
+        'T' => 1 << 40,
+        _ => return None,
+    };
+    let value: f64 = number.trim().parse().ok()?;
+    (value >= 0.0).then_some((value * multiplier as f64) as u64)
+}
+
+/// Start of a local date (or date and minute) as a Unix timestamp.
+pub fn parse_date(text: &str) -> Option<i64> {
+    let text = text.trim();
+    let datetime = NaiveDateTime::parse_from_str(text, "%Y-%m-%d %H:%M")
+        .ok()
+        .or_else(|| {
+            NaiveDate::parse_from_str(text, "%Y-%m-%d")
+                .ok()?
+                .and_hms_opt(0, 0, 0)
+        })?;
+    Local
+        .from_local_datetime(&datetime)
+        .earliest()
+        .map(|d| d.timestamp())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn stat(perm: u32, size: u64, mtime: u64) -> FileStat {
+        FileStat {
+            size: Some(size),
+            uid: None,
+            gid: None,
+            perm: Some(perm),
+            atime: None,
+            mtime: Some(mtime),
+        }
+    }
+
+    #[test]
+    fn test_parse_size() {
+        assert_eq!(parse_size("1500"), Some(1500));
+        assert_eq!(parse_size("10K"), Some(10 * 1024));
+        assert_eq!(parse_size("1.5m"), Some(3 * 512 * 1024));
+        assert_eq!(parse_size("2GiB"), Some(2 << 30));
+        assert_eq!(parse_size("10 KB"), Some(10 * 1024));
+        assert_eq!(parse_size("ten"), None);
+        assert_eq!(parse_size("5X"), None);
+    }
+
+    #[test]
+    fn test_filter_matches() {
+        let filter = SearchFilter {
+            pattern: r"^access\.log(\.\d+)?$".to_string(),
+            regex: true,
+            ignore_case: true,
+            recursive: true,
+            min_size: "1K".to_string(),
+            entry_type: EntryType::File,
+            exclude: ".git, node_modules".to_string(),
+            ..Default::default()
+        }
+        .compile()
+        .unwrap();
+        assert!(filter.matches("ACCESS.log.1", &stat(0o100644, 4096, 0)));
+        assert!(!filter.matches("access.log", &stat(0o100644, 10, 0)));
+        assert!(!filter.matches("access.log", &stat(0o040755, 4096, 0)));
+        assert!(!filter.matches("error.log", &stat(0o100644, 4096, 0)));
+        assert!(filter.is_excluded("node_modules"));
+        assert!(!filter.is_excluded("src"));
+        // A regex name cannot be passed to find, so only the rest narrows it
+        assert_eq!(
+            filter.find_args(),
+            [
+                "-maxdepth",
+                "64",
+                "(",
+                "-iname",
+                ".git",
+                "-o",
+                "-iname",
+                "node_modules",
+                ")",
+                "-prune",
+                "-o",
+                "-type",
+                "f",
+            ]
+        );
+
+        let glob = SearchFilter {
+            pattern: "[a]*.log".to_string(),
+            one_filesystem: true,
+            ..Default::default()
+        }
+        .compile()
+        .unwrap();
+        assert_eq!(
+            glob.find_args(),
+            ["-xdev", "-maxdepth", "1", "-name", "\\[a\\]*.log"]
+        );
+    }
+
+    #[test]
+    fn test_filter_dates_and_depth() {
+        let after = parse_date("2024-01-01").unwrap();
+        let filter = SearchFilter {
+            modified_after: "2024-01-01".to_string(),
+            recursive: true,
+            max_depth: "2".to_string(),
+            ..Default::default()
+        }
+        .compile()
+        .unwrap();
+        assert!(filter.matches("a", &stat(0o100644, 1, after as u64)));
+        assert!(!filter.matches("a", &stat(0o100644, 1, after as u64 - 1)));
+        assert!(filter.descends_into(1));
+        assert!(!filter.descends_into(2));
+
+        let shallow = SearchFilter::default().compile().unwrap();
+        assert!(!shallow.descends_into(1));
+
+        let invalid = SearchFilter {
+            modified_before: "yesterday".to_string(),
+            ..Default::default()
+        };
+        assert!(invalid.compile().is_err());
+    }
+}
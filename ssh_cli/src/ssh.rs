@@ -1,11 +1,15 @@
-use ssh2::{Session, Sftp, FileStat};
-use std::io::Read;
+use ssh2::{Session, Sftp, FileStat, FileType, OpenFlags, OpenType};
+use std::io::{Read, Seek, SeekFrom, Write};
 use std::net::TcpStream;
 use std::path::{Path, PathBuf};
+use std::sync::atomic::{AtomicBool, Ordering};
 use std::sync::{Arc, Mutex, mpsc};
 use std::fs::File;
-use crate::model::FileEntry;
+use crate::model::{FileEntry, IdNames, LineIndexUpdate, PermissionChange, SymlinkInfo};
+use crate::paged_view::index_chunk;
 use crate::app::AppMessage;
+use crate::search_filter::CompiledFilter;
+use crate::walk::{WalkGuard, WalkLimit, WalkOptions};
 
 /// SSH接続を確立し、SFTPセッションを初期化
 pub fn connect_session(host: &str, user: &str, pass: &str) -> anyhow::Result<(Session, Sftp, String)> {
@@ -51,12 +55,7 @@ pub fn list_files_streaming(
             continue;
         }
         
-        let file_entry = FileEntry {
-            perm: format_permissions(&stat),
-            size: stat.size.unwrap_or(0),
-            date: format_timestamp(stat.mtime),
-            name,
-        };
+        let file_entry = build_entry(&sftp, &entry_path, stat, name);
         
         batch.push(file_entry);
         if batch.len() >= 200 {
@@ -72,63 +71,201 @@ pub fn list_files_streaming(
     Ok(())
 }
 
-/// SFTP APIを使用してファイルを検索
+/// ディレクトリ直下のサブディレクトリ名を取得（パス補完用）
+/// ディレクトリを指すシンボリックリンクも含める
+pub fn list_subdirectories(sftp_arc: &Arc<Mutex<Sftp>>, path: &str) -> anyhow::Result<Vec<String>> {
+    let sftp = sftp_arc.lock().map_err(|_| anyhow::anyhow!("Lock error"))?;
+    let mut names = Vec::new();
+    for (entry_path, stat) in sftp.readdir(Path::new(path))? {
+        let Some(name) = entry_path.file_name().and_then(|n| n.to_str()) else {
+            continue;
+        };
+        if name == "." || name == ".." {
+            continue;
+        }
+        let is_dir = if stat.file_type().is_symlink() {
+            sftp.stat(&entry_path).map(|target| target.is_dir()).unwrap_or(false)
+        } else {
+            stat.is_dir()
+        };
+        if is_dir {
+            names.push(name.to_string());
+        }
+    }
+    Ok(names)
+}
+
+/// SFTP APIを使用してファイルを検索（見つかった順にディレクトリ単位で送信）
+/// ロックはreaddir 1回ごとに取得し、`cancel`で中断できる
+/// 条件はreaddirが返す属性だけで判定する（エントリごとのstatは行わない）
 pub fn search_files_streaming(
     sftp_arc: &Arc<Mutex<Sftp>>,
     base_path: &str,
-    pattern: &str,
-    recursive: bool,
+    filter: &CompiledFilter,
+    id: u64,
+    cancel: &AtomicBool,
     tx: mpsc::Sender<AppMessage>
 ) -> anyhow::Result<()> {
-    let _ = tx.send(AppMessage::SearchStarted(pattern.to_string()));
-    
-    let sftp = sftp_arc.lock().map_err(|_| anyhow::anyhow!("Lock error"))?;
-    
-    fn search_recursive(
-        sftp: &Sftp,
-        path: &Path,
-        pattern: &str,
-        recursive: bool,
-        results: &mut Vec<FileEntry>
-    ) -> anyhow::Result<()> {
-        let entries = sftp.readdir(path)?;
-        
+    let _ = tx.send(AppMessage::SearchStarted(id));
+
+    // ループ検出・ファイルシステム境界・深さと件数の上限
+    let (mut guard, base) = WalkGuard::start(sftp_arc, base_path, filter.walk)?;
+    let mut dirs = vec![base];
+    let mut scanned = 0;
+    let mut found = 0;
+    while let Some(dir) = dirs.pop() {
+        if cancel.load(Ordering::SeqCst) {
+            break;
+        }
+        let entries = {
+            let sftp = sftp_arc.lock().map_err(|_| anyhow::anyhow!("Lock error"))?;
+            match sftp.readdir(&dir.path) {
+                Ok(entries) => entries,
+                Err(e) if dir.depth == 1 => return Err(e.into()),
+                // 読めないサブディレクトリはスキップ
+                Err(_) => continue,
+            }
+        };
+        scanned += 1;
+
+        let mut batch = Vec::new();
+        let mut subdirs = Vec::new();
         for (entry_path, stat) in entries {
             let name = entry_path.file_name()
                 .and_then(|n| n.to_str())
-                .unwrap_or("");
-            
-            // "." と ".." をスキップ
-            if name == "." || name == ".." {
+                .unwrap_or("")
+                .to_string();
+
+            // "." と ".." および除外パターンに一致するものをスキップ
+            if name == "." || name == ".." || filter.is_excluded(&name) {
                 continue;
             }
-            
-            // パターンマッチング
-            if matches_pattern(name, pattern) {
-                results.push(FileEntry {
-                    perm: format_permissions(&stat),
-                    size: stat.size.unwrap_or(0),
-                    date: format_timestamp(stat.mtime),
-                    name: name.to_string(),
-                });
+            if !guard.count_entry() {
+                break;
             }
-            
-            // 再帰的検索
-            if recursive && stat.is_dir() {
-                let _ = search_recursive(sftp, &entry_path, pattern, recursive, results);
+
+            // 再帰的検索（シンボリックリンク先はオプション指定時のみ辿る）
+            if filter.descends_into(dir.depth)
+                && let Some(subdir) = guard.descend(sftp_arc, &dir, &entry_path, &stat)? {
+                subdirs.push(subdir);
+            }
+
+            // 名前・種類・サイズ・更新日時の条件
+            if filter.matches(&name, &stat) {
+                let sftp = sftp_arc.lock().map_err(|_| anyhow::anyhow!("Lock error"))?;
+                batch.push(build_entry(&sftp, &entry_path, stat, name));
             }
         }
-        Ok(())
+
+        // 深さ優先・名前順に辿る
+        subdirs.sort_by(|a, b| a.path.cmp(&b.path));
+        dirs.extend(subdirs.into_iter().rev());
+
+        // 中断後の結果は次の一覧に混ざらないよう送らない
+        if cancel.load(Ordering::SeqCst) {
+            break;
+        }
+        found += batch.len();
+        if !batch.is_empty() {
+            let _ = tx.send(AppMessage::SearchBatch(id, batch));
+        }
+        let _ = tx.send(AppMessage::SearchProgress(id, scanned, found));
+        if guard.limit == Some(WalkLimit::Entries) {
+            break;
+        }
     }
-    
-    let mut results = Vec::new();
-    search_recursive(&sftp, Path::new(base_path), pattern, recursive, &mut results)?;
-    
-    // バッチ送信
-    for chunk in results.chunks(200) {
-        let _ = tx.send(AppMessage::ListBatch(chunk.to_vec()));
+
+    let _ = tx.send(AppMessage::SearchFinished(id, scanned, cancel.load(Ordering::SeqCst), guard.limit));
+    Ok(())
+}
+
+/// readdirの結果からFileEntryを作成（シンボリックリンクはリンク先も解決）
+fn build_entry(sftp: &Sftp, entry_path: &Path, stat: FileStat, name: String) -> FileEntry {
+    // readdirが属性を返さないサーバーではlstatで補う
+    let stat = if stat.perm.is_none() {
+        sftp.lstat(entry_path).unwrap_or(stat)
+    } else {
+        stat
+    };
+
+    let link = if stat.file_type().is_symlink() {
+        Some(resolve_symlink(sftp, entry_path))
+    } else {
+        None
+    };
+    entry_from_stat(entry_path, stat, name, link)
+}
+
+/// 属性からFileEntryを作成（リンク先はサーバー側findなど呼び出し元で解決済み）
+pub fn entry_from_stat(entry_path: &Path, stat: FileStat, name: String, link: Option<SymlinkInfo>) -> FileEntry {
+    FileEntry {
+        perm: format_permissions(&stat),
+        size: stat.size.unwrap_or(0),
+        date: format_timestamp(stat.mtime),
+        name,
+        path: entry_path.to_string_lossy().into_owned(),
+        link,
+        mode: stat.perm,
+        uid: stat.uid,
+        gid: stat.gid,
+        atime: stat.atime,
+        mtime: stat.mtime,
     }
-    let _ = tx.send(AppMessage::ListFinished);
+}
+
+/// readlinkでリンク先を取得し、statでリンク先の種類を判定
+fn resolve_symlink(sftp: &Sftp, link_path: &Path) -> SymlinkInfo {
+    let target = sftp.readlink(link_path)
+        .map(|t| t.to_string_lossy().into_owned())
+        .unwrap_or_else(|_| "?".to_string());
+
+    // stat()はリンクを辿るため、失敗した場合はリンク切れ
+    match sftp.stat(link_path) {
+        Ok(target_stat) => SymlinkInfo {
+            target,
+            target_is_dir: target_stat.is_dir(),
+            broken: false,
+        },
+        Err(_) => SymlinkInfo {
+            target,
+            target_is_dir: false,
+            broken: true,
+        },
+    }
+}
+
+/// /etc/passwd と /etc/group をSFTPで読み、uid/gidと名前の対応表を作成
+/// 読めないファイルは空の表として扱う（数値IDで表示される）
+pub fn load_id_names(sftp_arc: &Arc<Mutex<Sftp>>) -> anyhow::Result<IdNames> {
+    let sftp = sftp_arc.lock().map_err(|_| anyhow::anyhow!("Lock error"))?;
+
+    let read_table = |path: &str| -> std::collections::HashMap<u32, String> {
+        fn read_text(sftp: &Sftp, path: &str) -> anyhow::Result<String> {
+            let mut content = String::new();
+            sftp.open(Path::new(path))?.read_to_string(&mut content)?;
+            Ok(content)
+        }
+
+        read_text(&sftp, path)
+            .map(|content| parse_id_table(&content))
+            .unwrap_or_default()
+    };
+
+    Ok(IdNames {
+        users: read_table("/etc/passwd"),
+        groups: read_table("/etc/group"),
+    })
+}
+
+/// SFTP APIを使用してシンボリックリンクを作成
+pub fn create_symlink(
+    sftp_arc: &Arc<Mutex<Sftp>>,
+    link_path: &str,
+    target: &str
+) -> anyhow::Result<()> {
+    let sftp = sftp_arc.lock().map_err(|_| anyhow::anyhow!("Lock error"))?;
+    // ssh2のsymlink(path, target)は「targetにpathを指すリンクを作成」する
+    sftp.symlink(Path::new(target), Path::new(link_path))?;
     Ok(())
 }
 
@@ -144,33 +281,308 @@ pub fn download_worker(session: Arc<Mutex<Session>>, remote_path: &str, local_pa
 }
 
 /// SFTP APIを使用してファイル内容を読み取る
+/// `extended_limit`は先頭`max_bytes`とファイルサイズから読み取り上限を決め直す（画像の全体取得用）
 pub fn read_file_content(
     sftp_arc: &Arc<Mutex<Sftp>>,
     remote_path: &str,
     max_bytes: usize,
+    extended_limit: impl Fn(&[u8], u64) -> usize,
     tx: mpsc::Sender<AppMessage>
 ) -> anyhow::Result<()> {
     let sftp = sftp_arc.lock().map_err(|_| anyhow::anyhow!("Lock error"))?;
     let mut file = sftp.open(Path::new(remote_path))?;
+    // 保存時の競合検出・切り詰め判定のため、読み込み時の属性も返す
+    let stat = file.stat()?;
     
-    // 最大バイト数まで読み取り
-    let mut buffer = vec![0u8; max_bytes];
-    let bytes_read = file.read(&mut buffer)?;
-    let content = buffer[..bytes_read].to_vec();
+    // 最大バイト数まで読み取り（1回のread()は途中までしか返さないことがある）
+    let mut content = read_up_to(&mut file, max_bytes as u64)?;
+    let limit = extended_limit(&content, stat.size.unwrap_or(0));
+    if content.len() == max_bytes && limit > max_bytes {
+        content.extend(read_up_to(&mut file, (limit - max_bytes) as u64)?);
+    }
     
-    let _ = tx.send(AppMessage::FileContentResult(Ok((remote_path.to_string(), content))));
+    let _ = tx.send(AppMessage::FileContentResult(Ok((remote_path.to_string(), content, stat))));
     Ok(())
 }
 
+/// SFTP APIを使用してファイルの指定範囲を読み取る（ページ表示用）
+pub fn read_file_range(
+    sftp_arc: &Arc<Mutex<Sftp>>,
+    remote_path: &str,
+    offset: u64,
+    len: u64
+) -> anyhow::Result<Vec<u8>> {
+    let sftp = sftp_arc.lock().map_err(|_| anyhow::anyhow!("Lock error"))?;
+    let mut file = sftp.open(Path::new(remote_path))?;
+    file.seek(SeekFrom::Start(offset))?;
+    read_up_to(&mut file, len)
+}
+
+/// 読み取り1回ごとにSFTPのロックを取得するリモートファイル
+/// 長時間の読み取り中も他の操作を妨げない
+pub struct LockedRemoteFile {
+    sftp_arc: Arc<Mutex<Sftp>>,
+    file: ssh2::File,
+}
+
+impl Read for LockedRemoteFile {
+    fn read(&mut self, buf: &mut [u8]) -> std::io::Result<usize> {
+        let _sftp = self.sftp_arc.lock().map_err(|_| std::io::Error::other("Lock error"))?;
+        self.file.read(buf)
+    }
+}
+
+/// ファイルを開き、ロック付きリーダーと属性を返す
+pub fn open_locked(
+    sftp_arc: &Arc<Mutex<Sftp>>,
+    remote_path: &str
+) -> anyhow::Result<(LockedRemoteFile, FileStat)> {
+    let sftp = sftp_arc.lock().map_err(|_| anyhow::anyhow!("Lock error"))?;
+    let mut file = sftp.open(Path::new(remote_path))?;
+    let stat = file.stat()?;
+    Ok((LockedRemoteFile { sftp_arc: sftp_arc.clone(), file }, stat))
+}
+
+/// 内容全体を順に読み、行インデックスをバックグラウンドで作成
+/// `remote_path`は進捗の宛先を示すキー（読み取り元は`reader`）
+pub fn build_line_index(
+    mut reader: impl Read,
+    remote_path: &str,
+    cancel: &AtomicBool,
+    tx: mpsc::Sender<AppMessage>
+) -> anyhow::Result<()> {
+    const CHUNK_SIZE: u64 = 4 * 1024 * 1024;
+
+    let mut scanned = 0u64;
+    let mut lines = 0u64;
+    let mut last_byte = None;
+    loop {
+        if cancel.load(Ordering::SeqCst) {
+            return Ok(());
+        }
+        let chunk = read_up_to(&mut reader, CHUNK_SIZE)?;
+        if chunk.is_empty() {
+            break;
+        }
+
+        let mut checkpoints = Vec::new();
+        index_chunk(&chunk, scanned, &mut lines, &mut checkpoints);
+        scanned += chunk.len() as u64;
+        last_byte = chunk.last().copied();
+
+        let _ = tx.send(AppMessage::LineIndexProgress(remote_path.to_string(), LineIndexUpdate {
+            checkpoints,
+            scanned_bytes: scanned,
+            total_lines: None,
+        }));
+    }
+
+    // 改行で終わらない最終行も1行と数える
+    let total_lines = lines + u64::from(last_byte.is_some_and(|b| b != b'\n'));
+    let _ = tx.send(AppMessage::LineIndexProgress(remote_path.to_string(), LineIndexUpdate {
+        checkpoints: Vec::new(),
+        scanned_bytes: scanned,
+        total_lines: Some(total_lines),
+    }));
+    Ok(())
+}
+
+/// 最大`len`バイトまで、EOFに達するまで繰り返し読み取る
+pub fn read_up_to(reader: &mut impl Read, len: u64) -> anyhow::Result<Vec<u8>> {
+    let mut buffer = Vec::new();
+    reader.take(len).read_to_end(&mut buffer)?;
+    Ok(buffer)
+}
+
+/// 読み込み後にリモートファイルが変更されていた場合のエラー
+#[derive(Debug)]
+pub struct RemoteModifiedError;
+
+impl std::fmt::Display for RemoteModifiedError {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "Remote file was modified since it was loaded")
+    }
+}
+
+impl std::error::Error for RemoteModifiedError {}
+
+/// SFTP APIを使用して編集内容を書き戻す
+/// `expected_mtime`が指定されていればリモートのmtimeと比較し、異なれば`RemoteModifiedError`で中断する
+/// `backup`が指定されていれば上書き前にその内容を元ファイルと同じパーミッションの`<path>.bak`へ退避し、保存後の新しいmtimeを返す
+pub fn write_file_content(
+    sftp_arc: &Arc<Mutex<Sftp>>,
+    remote_path: &str,
+    content: &[u8],
+    backup: Option<&[u8]>,
+    expected_mtime: Option<u64>
+) -> anyhow::Result<Option<u64>> {
+    let sftp = sftp_arc.lock().map_err(|_| anyhow::anyhow!("Lock error"))?;
+    let path = Path::new(remote_path);
+
+    if let Some(expected) = expected_mtime {
+        let current = sftp.stat(path)?;
+        if current.mtime != Some(expected) {
+            return Err(RemoteModifiedError.into());
+        }
+    }
+
+    // バックアップ作成（元ファイルと同じパーミッションにしてから書き込む）
+    if let Some(original) = backup {
+        let mode = sftp.stat(path)?.perm.unwrap_or(0o600) & 0o7777;
+        let backup_path = format!("{}.bak", remote_path);
+        let mut backup_file = sftp.open_mode(
+            Path::new(&backup_path),
+            OpenFlags::WRITE | OpenFlags::CREATE | OpenFlags::TRUNCATE,
+            (mode & 0o777) as i32,
+            OpenType::File,
+        )?;
+        // 既存の.bakや umask の影響を受けないよう明示的に設定する
+        backup_file.setstat(FileStat {
+            size: None,
+            uid: None,
+            gid: None,
+            perm: Some(mode),
+            atime: None,
+            mtime: None,
+        })?;
+        backup_file.write_all(original)?;
+    }
+
+    // 既存ファイルをその場で切り詰めて書き込む（パーミッション・所有者を維持）
+    let mut file = sftp.open_mode(path, OpenFlags::WRITE | OpenFlags::TRUNCATE, 0o644, OpenType::File)?;
+    file.write_all(content)?;
+    drop(file);
+
+    Ok(sftp.stat(path)?.mtime)
+}
+
+/// SFTP APIを使用してファイルをローカルへ取得し、リモートのmtimeを返す（外部エディタ編集用）
+pub fn download_to_local(
+    sftp_arc: &Arc<Mutex<Sftp>>,
+    remote_path: &str,
+    local_path: &Path
+) -> anyhow::Result<Option<u64>> {
+    let sftp = sftp_arc.lock().map_err(|_| anyhow::anyhow!("Lock error"))?;
+    let mut remote_file = sftp.open(Path::new(remote_path))?;
+    let stat = remote_file.stat()?;
+
+    let mut local_file = File::create(local_path)?;
+    std::io::copy(&mut remote_file, &mut local_file)?;
+
+    Ok(stat.mtime)
+}
+
+/// SFTP APIを使用してファイル属性を取得（プロパティダイアログ用）
+pub fn stat_path(
+    sftp_arc: &Arc<Mutex<Sftp>>,
+    remote_path: &str,
+    tx: mpsc::Sender<AppMessage>
+) -> anyhow::Result<()> {
+    let sftp = sftp_arc.lock().map_err(|_| anyhow::anyhow!("Lock error"))?;
+    let stat = sftp.stat(Path::new(remote_path))?;
+
+    let _ = tx.send(AppMessage::PropertiesResult(Ok((remote_path.to_string(), stat))));
+    Ok(())
+}
+
+/// SFTP setstatでパーミッション・所有者を変更（chmod / chown）
+/// 変更したエントリ数と、上限で打ち切った場合はその理由を返す
+pub fn apply_permissions(
+    sftp_arc: &Arc<Mutex<Sftp>>,
+    remote_path: &str,
+    change: &PermissionChange
+) -> anyhow::Result<(usize, Option<WalkLimit>)> {
+    fn set_attributes(
+        sftp_arc: &Arc<Mutex<Sftp>>,
+        path: &Path,
+        stat: &FileStat,
+        change: &PermissionChange
+    ) -> anyhow::Result<()> {
+        let mode = if stat.is_dir() { change.dir_mode } else { change.file_mode };
+        // UID/GIDは同じフラグで送られるため、片方だけの変更でも両方を埋める
+        let (uid, gid) = if change.uid.is_some() || change.gid.is_some() {
+            (change.uid.or(stat.uid), change.gid.or(stat.gid))
+        } else {
+            (None, None)
+        };
+
+        let sftp = sftp_arc.lock().map_err(|_| anyhow::anyhow!("Lock error"))?;
+        sftp.setstat(path, FileStat {
+            size: None,
+            uid,
+            gid,
+            perm: Some(mode & 0o7777),
+            atime: None,
+            mtime: None,
+        })?;
+        Ok(())
+    }
+
+    let path = Path::new(remote_path);
+    let stat = {
+        let sftp = sftp_arc.lock().map_err(|_| anyhow::anyhow!("Lock error"))?;
+        sftp.stat(path)?
+    };
+    set_attributes(sftp_arc, path, &stat, change)?;
+
+    let mut count = 1;
+    if !change.recursive || !stat.is_dir() {
+        return Ok((count, None));
+    }
+
+    // /procやNFSなど別のマウントには入らない（マウントポイント自体は変更する、find -xdev と同じ）
+    // シンボリックリンクは辿らない
+    let options = WalkOptions { follow_links: false, one_filesystem: true };
+    let (mut guard, base) = WalkGuard::start(sftp_arc, remote_path, options)?;
+    let mut dirs = vec![base];
+    while let Some(dir) = dirs.pop() {
+        // ロックはreaddir・setstat単位で取り、変更中も他の操作を止めない
+        let entries = {
+            let sftp = sftp_arc.lock().map_err(|_| anyhow::anyhow!("Lock error"))?;
+            sftp.readdir(&dir.path)?
+        };
+        for (entry_path, stat) in entries {
+            let name = entry_path.file_name()
+                .and_then(|n| n.to_str())
+                .unwrap_or("");
+
+            // "." と ".." をスキップ
+            if name == "." || name == ".." {
+                continue;
+            }
+            if !guard.count_entry() {
+                return Ok((count, guard.limit));
+            }
+
+            // シンボリックリンクはリンク先を変更してしまうため対象外
+            if stat.file_type().is_symlink() {
+                continue;
+            }
+
+            set_attributes(sftp_arc, &entry_path, &stat, change)?;
+            count += 1;
+
+            if let Some(subdir) = guard.descend(sftp_arc, &dir, &entry_path, &stat)? {
+                dirs.push(subdir);
+            }
+        }
+    }
+    Ok((count, guard.limit))
+}
+
 /// パーミッションを文字列形式に変換（例: drwxr-xr-x）
 fn format_permissions(stat: &FileStat) -> String {
     let perm = stat.perm.unwrap_or(0);
     
     // ファイルタイプ判定
-    let file_type = if stat.is_dir() {
-        'd'
-    } else {
-        '-'
+    let file_type = match stat.file_type() {
+        FileType::Directory => 'd',
+        FileType::Symlink => 'l',
+        FileType::NamedPipe => 'p',
+        FileType::CharDevice => 'c',
+        FileType::BlockDevice => 'b',
+        FileType::Socket => 's',
+        FileType::RegularFile | FileType::Other(_) => '-',
     };
     
     // ユーザー権限
@@ -199,20 +611,58 @@ fn format_permissions(stat: &FileStat) -> String {
 
 /// Unixタイムスタンプを日付文字列に変換
 fn format_timestamp(mtime: Option<u64>) -> String {
-    use chrono::{DateTime, Utc, TimeZone};
+    use chrono::{Utc, TimeZone};
     
     if let Some(timestamp) = mtime {
         let dt = Utc.timestamp_opt(timestamp as i64, 0)
             .single()
-            .unwrap_or_else(|| Utc::now());
+            .unwrap_or_else(Utc::now);
         dt.format("%b %d %H:%M").to_string()
     } else {
         "Unknown".to_string()
     }
 }
 
+/// Unixタイムスタンプを秒まで含む日時文字列に変換（詳細表示用）
+pub fn format_timestamp_full(time: Option<u64>) -> String {
+    use chrono::{Utc, TimeZone};
+
+    match time.and_then(|t| Utc.timestamp_opt(t as i64, 0).single()) {
+        Some(dt) => format!("{} ({})", dt.format("%Y-%m-%d %H:%M:%S UTC"), dt.timestamp()),
+        None => "Unknown".to_string(),
+    }
+}
+
+/// passwd/group形式（name:x:id:...）を解析してidと名前の対応表を作成
+fn parse_id_table(content: &str) -> std::collections::HashMap<u32, String> {
+    content
+        .lines()
+        .filter(|line| !line.starts_with('#'))
+        .filter_map(|line| {
+            let mut fields = line.split(':');
+            let name = fields.next()?;
+            let id = fields.nth(1)?.parse::<u32>().ok()?;
+            Some((id, name.to_string()))
+        })
+        .collect()
+}
+
+/// モードを4桁の8進数文字列に変換（例: 0755）
+pub fn format_mode_octal(mode: u32) -> String {
+    format!("{:04o}", mode & 0o7777)
+}
+
+/// 8進数文字列をモードに変換（特殊ビットまで、0o7777以下のみ有効）
+pub fn parse_mode_octal(text: &str) -> Option<u32> {
+    let text = text.trim();
+    if text.is_empty() || text.len() > 4 {
+        return None;
+    }
+    u32::from_str_radix(text, 8).ok()
+}
+
 /// Globパターンマッチング（*と?をサポート）
-fn matches_pattern(name: &str, pattern: &str) -> bool {
+pub fn matches_pattern(name: &str, pattern: &str) -> bool {
     // パターンを正規表現に変換
     let pattern_escaped = regex::escape(pattern)
         .replace(r"\*", ".*")
@@ -230,6 +680,7 @@ mod tests {
     use super::*;
 
     #[test]
+    #[allow(unused_assignments)]
     fn test_format_permissions() {
         let mut stat = FileStat {
             size: Some(0),
@@ -245,6 +696,23 @@ mod tests {
         // is_dir()はpermだけでは判定できないため、手動設定が必要
         // このテストは実際のFileStatでは動作しない可能性がある
     }
+
+    #[test]
+    fn test_format_permissions_file_types() {
+        // is_dir()はpermのファイルタイプビット（S_IFDIR）から判定される
+        let mut stat = FileStat {
+            size: Some(0),
+            uid: None,
+            gid: None,
+            perm: Some(0o040755),
+            atime: None,
+            mtime: None,
+        };
+        assert_eq!(format_permissions(&stat), "drwxr-xr-x");
+
+        stat.perm = Some(0o120777);
+        assert_eq!(format_permissions(&stat), "lrwxrwxrwx");
+    }
     
     #[test]
     fn test_matches_pattern() {
@@ -254,6 +722,70 @@ mod tests {
         assert!(matches_pattern("readme", "*"));
     }
     
+    #[test]
+    fn test_mode_octal_round_trip() {
+        assert_eq!(format_mode_octal(0o100644), "0644");
+        assert_eq!(format_mode_octal(0o4755), "4755");
+        assert_eq!(parse_mode_octal("755"), Some(0o755));
+        assert_eq!(parse_mode_octal(" 1777 "), Some(0o1777));
+        assert_eq!(parse_mode_octal("0888"), None);
+        assert_eq!(parse_mode_octal("10755"), None);
+        assert_eq!(parse_mode_octal(""), None);
+    }
+
+    #[test]
+    fn test_parse_id_table() {
+        let passwd = "root:x:0:0:root:/root:/bin/bash\n# comment\nwww-data:x:33:33::/var/www:/usr/sbin/nologin\nbroken line\n";
+        let table = parse_id_table(passwd);
+        assert_eq!(table.get(&0).map(String::as_str), Some("root"));
+        assert_eq!(table.get(&33).map(String::as_str), Some("www-data"));
+        assert_eq!(table.len(), 2);
+
+        let group = "wheel:x:10:alice,bob\n";
+        assert_eq!(parse_id_table(group).get(&10).map(String::as_str), Some("wheel"));
+    }
+
+    #[test]
+    fn test_format_timestamp_full() {
+        assert_eq!(
+            format_timestamp_full(Some(1704067200)),
+            "2024-01-01 00:00:00 UTC (1704067200)"
+        );
+        assert_eq!(format_timestamp_full(None), "Unknown");
+    }
+
+    #[test]
+    #[ignore = "needs a local sshd; see README"]
+    fn test_create_symlink_argument_order() {
+        // OpenSSHはSSH_FXP_SYMLINKの引数を仕様と逆に解釈するため、実サーバーで向きを確認する
+        let (_session, sftp_arc, home) = crate::remote_find::tests::test_server();
+        let base = format!(
+            "{}/ssh_cli_link_test_{}_{}",
+            home,
+            std::process::id(),
+            chrono::Utc::now().timestamp_millis()
+        );
+        let link = format!("{}/link", base);
+        let target_file = format!("{}/target.txt", base);
+        {
+            let sftp = sftp_arc.lock().unwrap();
+            sftp.mkdir(Path::new(&base), 0o755).unwrap();
+            sftp.create(Path::new(&target_file)).unwrap();
+        }
+
+        let created = create_symlink(&sftp_arc, &link, "target.txt");
+        let sftp = sftp_arc.lock().unwrap();
+        let target = sftp.readlink(Path::new(&link));
+        let stat = sftp.lstat(Path::new(&link));
+        let _ = sftp.unlink(Path::new(&link));
+        let _ = sftp.unlink(Path::new(&target_file));
+        let _ = sftp.rmdir(Path::new(&base));
+
+        created.unwrap();
+        assert_eq!(target.unwrap(), PathBuf::from("target.txt"));
+        assert!(stat.unwrap().file_type().is_symlink());
+    }
+
     #[test]
     fn test_format_timestamp() {
         let timestamp = 1704067200u64; // 2024-01-01 00:00:00 UTC
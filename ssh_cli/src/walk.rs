@@ -0,0 +1,240 @@
+use ssh2::{FileStat, Sftp};
+use std::collections::HashSet;
+use std::path::{Path, PathBuf};
+use std::sync::{Arc, Mutex};
+
+use crate::ssh::read_up_to;
+
+/// Directories nested deeper than this below the start are never entered.
+pub const MAX_WALK_DEPTH: usize = 64;
+/// A walk stops after examining this many entries.
+pub const MAX_WALK_ENTRIES: usize = 1_000_000;
+/// Treated as mount points when the server's mount table cannot be read.
+const PSEUDO_FILESYSTEMS: &[&str] = &["/", "/proc", "/sys", "/dev", "/run"];
+
+/// Why a walk ended before covering the whole tree.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum WalkLimit {
+    Depth,
+    Entries,
+}
+
+impl std::fmt::Display for WalkLimit {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            WalkLimit::Depth => write!(f, "not entered below depth {}", MAX_WALK_DEPTH),
+            WalkLimit::Entries => write!(f, "stopped after {} entries", MAX_WALK_ENTRIES),
+        }
+    }
+}
+
+/// Options shared by every recursive walk.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub struct WalkOptions {
+    /// Enter symlinks that point to directories
+    pub follow_links: bool,
+    /// Do not cross into other mounts such as /proc or network filesystems
+    pub one_filesystem: bool,
+}
+
+/// A directory queued for walking.
+#[derive(Debug, Clone)]
+pub struct WalkDir {
+    /// Path as reached, through any followed links
+    pub path: PathBuf,
+    /// Resolved path, used to recognize directories already walked
+    pub canonical: PathBuf,
+    /// Depth of the entries inside it (1 for the starting directory)
+    pub depth: usize,
+}
+
+/// Safeguards for walking a remote tree: loop detection keyed on resolved
+/// paths, an optional filesystem boundary, and hard caps on depth and
+/// entry count.
+pub struct WalkGuard {
+    options: WalkOptions,
+    visited: HashSet<PathBuf>,
+    /// Mount points, deepest first; empty unless staying on one filesystem
+    mounts: Vec<PathBuf>,
+    base_mount: Option<PathBuf>,
+    entries: usize,
+    /// Set when a cap cut the walk short
+    pub limit: Option<WalkLimit>,
+}
+
+impl WalkGuard {
+    /// Resolves the starting directory and, if needed, reads the mount table.
+    pub fn start(
+        sftp_arc: &Arc<Mutex<Sftp>>,
+        base_path: &str,
+        options: WalkOptions,
+    ) -> anyhow::Result<(Self, WalkDir)> {
+        let sftp = sftp_arc.lock().map_err(|_| anyhow::anyhow!("Lock error"))?;
+        let canonical = sftp.realpath(Path::new(base_path))?;
+        let mounts = if options.one_filesystem {
+            read_mounts(&sftp)
+        } else {
+            Vec::new()
+        };
+        drop(sftp);
+
+        let mut guard = Self {
+            options,
+            visited: HashSet::new(),
+            mounts,
+            base_mount: None,
+            entries: 0,
+            limit: None,
+        };
+        guard.base_mount = guard.mount_of(&canonical);
+        guard.visited.insert(canonical.clone());
+        let base = WalkDir {
+            path: PathBuf::from(base_path),
+            canonical,
+            depth: 1,
+        };
+        Ok((guard, base))
+    }
+
+    /// Counts one examined entry; false once MAX_WALK_ENTRIES is exceeded.
+    pub fn count_entry(&mut self) -> bool {
+        self.entries += 1;
+        if self.entries > MAX_WALK_ENTRIES {
+            self.limit = Some(WalkLimit::Entries);
+            return false;
+        }
+        true
+    }
+
+    /// The entry as a directory to walk, or None if it is not a directory or
+    /// a safeguard rules it out. `stat` is the unfollowed one from readdir.
+    pub fn descend(
+        &mut self,
+        sftp_arc: &Arc<Mutex<Sftp>>,
+        parent: &WalkDir,
+        path: &Path,
+        stat: &FileStat,
+    ) -> anyhow::Result<Option<WalkDir>> {
+        let canonical = if stat.file_type().is_symlink() {
+            if !self.options.follow_links {
+                return Ok(None);
+            }
+            let sftp = sftp_arc.lock().map_err(|_| anyhow::anyhow!("Lock error"))?;
+            // Broken links and links to files are not walked
+            match (sftp.stat(path), sftp.realpath(path)) {
+                (Ok(target), Ok(resolved)) if target.is_dir() => resolved,
+                _ => return Ok(None),
+            }
+        } else if stat.is_dir() {
+            let Some(name) = path.file_name() else {
+                return Ok(None);
+            };
+            parent.canonical.join(name)
+        } else {
+            return Ok(None);
+        };
+
+        if parent.depth >= MAX_WALK_DEPTH {
+            self.limit.get_or_insert(WalkLimit::Depth);
+            return Ok(None);
+        }
+        if self.options.one_filesystem && self.mount_of(&canonical) != self.base_mount {
+            return Ok(None);
+        }
+        // A directory seen before is a loop, or was already reached another way
+        if !self.visited.insert(canonical.clone()) {
+            return Ok(None);
+        }
+        Ok(Some(WalkDir {
+            path: path.to_path_buf(),
+            canonical,
+            depth: parent.depth + 1,
+        }))
+    }
+
+    fn mount_of(&self, path: &Path) -> Option<PathBuf> {
+        self.mounts.iter().find(|m| path.starts_with(m)).cloned()
+    }
+}
+
+/// Mount points from the server's mount table, deepest first.
+fn read_mounts(sftp: &Sftp) -> Vec<PathBuf> {
+    let table = sftp
+        .open(Path::new("/proc/self/mounts"))
+        .ok()
+        .and_then(|mut file| read_up_to(&mut file, 4 * 1024 * 1024).ok())
+        .map(|bytes| parse_mounts(&String::from_utf8_lossy(&bytes)))
+        .filter(|mounts| !mounts.is_empty());
+    let mut mounts =
+        table.unwrap_or_else(|| PSEUDO_FILESYSTEMS.iter().map(PathBuf::from).collect());
+    mounts.sort_by_key(|m| std::cmp::Reverse(m.components().count()));
+    mounts
+}
+
+/// Mount points in /proc/mounts format, where spaces and the like are
+/// written as octal escapes (`\040`).
+pub fn parse_mounts(table: &str) -> Vec<PathBuf> {
+    table
+        .lines()
+        .filter_map(|line| line.split_whitespace().nth(1))
+        .map(|field| PathBuf::from(unescape_octal(field)))
+        .collect()
+}
+
+fn unescape_octal(field: &str) -> String {
+    let bytes = field.as_bytes();
+    let mut out = Vec::with_capacity(bytes.len());
+    let mut i = 0;
+    while i < bytes.len() {
+        let escape = bytes.get(i + 1..i + 4).filter(|_| bytes[i] == b'\\');
+        match escape
+            .and_then(|digits| std::str::from_utf8(digits).ok())
+            .and_then(|digits| u8::from_str_radix(digits, 8).ok())
+        {
+            Some(byte) => {
+                out.push(byte);
+                i += 4;
+            }
+            None => {
+                out.push(bytes[i]);
+                i += 1;
+            }
+        }
+    }
+    String::from_utf8_lossy(&out).into_owned()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_parse_mounts() {
+        let table = "/dev/sda1 / ext4 rw 0 0\n\
+            proc /proc proc rw 0 0\n\
+            server:/share /mnt/my\\040share nfs4 rw 0 0\n";
+        assert_eq!(
+            parse_mounts(table),
+            ["/", "/proc", "/mnt/my share"].map(PathBuf::from)
+        );
+    }
+
+    #[test]
+    fn test_mount_boundaries() {
+        let mut mounts = parse_mounts("a / ext4\nb /home ext4\nc /home/nfs nfs\n");
+        mounts.sort_by_key(|m| std::cmp::Reverse(m.components().count()));
+        let guard = WalkGuard {
+            options: WalkOptions::default(),
+            visited: HashSet::new(),
+            mounts,
+            base_mount: None,
+            entries: 0,
+            limit: None,
+        };
+        let mount = |p: &str| guard.mount_of(Path::new(p));
+        assert_eq!(mount("/home/user/src"), Some(PathBuf::from("/home")));
+        assert_eq!(mount("/home/nfs/x"), Some(PathBuf::from("/home/nfs")));
+        // Component-wise: "/homework" is not under "/home"
+        assert_eq!(mount("/homework"), Some(PathBuf::from("/")));
+    }
+}